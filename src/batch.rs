@@ -0,0 +1,221 @@
+use crate::{
+    downloader::Downloader,
+    error::Error,
+    models::Game,
+    queue::download_queue,
+    search::{find_game, search_games},
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Maximum number of candidates listed for an ambiguous line.
+const MAX_CANDIDATES: usize = 5;
+
+/// How a line of a batch file was matched against the catalogue.
+pub enum Resolution<'a> {
+    /// Exactly one game matched
+    Found(&'a Game),
+    /// Several games matched; the line needs to be more specific
+    Ambiguous(Vec<&'a Game>),
+    /// Nothing matched
+    NoMatch,
+}
+
+/// Final state of one line of a batch file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Downloaded,
+    /// Dry run found nothing that would make the download fail
+    Planned,
+    Failed,
+    Ambiguous,
+    NoMatch,
+    /// The line resolved to a game already requested by an earlier line
+    Duplicate,
+}
+
+/// Report entry for one line of a batch file.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchLine {
+    /// 1-based line number in the file
+    pub line: usize,
+    /// The line as written
+    pub input: String,
+    /// The resolved title, if any
+    pub title: Option<String>,
+    pub status: BatchStatus,
+    /// Error message, or the candidates of an ambiguous line
+    pub detail: Option<String>,
+}
+
+/// Reads the non-empty, non-comment (`#`) lines of a batch file with their line numbers.
+pub fn read_batch_file(path: &Path) -> Result<Vec<(usize, String)>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::filesystem("read batch file", path, e))?;
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim().to_string()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect())
+}
+
+/// Resolves a line as an exact Redump title, a title ID (e.g. BLUS-30001) or search terms.
+/// For search terms, the line is ambiguous when several games share the best score.
+pub fn resolve_line<'a>(games: &'a [Game], input: &str) -> Resolution<'a> {
+    // Numbers are never catalogue indexes here, only parts of titles
+    if input.parse::<usize>().is_err() {
+        if let Some(game) = find_game(games, input) {
+            return Resolution::Found(game);
+        }
+    }
+
+    let matches: Vec<&Game> = match normalize_title_id(input) {
+        Some(title_id) => games
+            .iter()
+            .filter(|game| {
+                game.title
+                    .to_uppercase()
+                    .replace('-', "")
+                    .contains(&title_id)
+            })
+            .collect(),
+        // Only the best-scoring matches count, so a close typo does not make a line ambiguous
+        None => {
+            let hits = search_games(games, input);
+            let best = hits.first().map_or(0.0, |hit| hit.score);
+            hits.into_iter()
+                .take_while(|hit| hit.score == best)
+                .map(|hit| hit.game)
+                .collect()
+        }
+    };
+
+    match matches.len() {
+        0 => Resolution::NoMatch,
+        1 => Resolution::Found(matches[0]),
+        _ => Resolution::Ambiguous(matches),
+    }
+}
+
+/// Returns the title ID without its dash (e.g. BLUS30001) if the input looks like one.
+fn normalize_title_id(input: &str) -> Option<String> {
+    let id = input.trim().to_uppercase().replace('-', "");
+    let (prefix, number) = id.split_at_checked(4)?;
+    if prefix.chars().all(|c| c.is_ascii_uppercase())
+        && number.len() == 5
+        && number.chars().all(|c| c.is_ascii_digit())
+    {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Resolves every line of the file, downloads the resolved games one after another
+/// without prompting, and returns the per-line report.
+pub async fn run_batch(downloader: &Downloader, games: &[Game], path: &Path) -> Result<Vec<BatchLine>> {
+    let lines = read_batch_file(path)?;
+    let reporter = downloader.reporter();
+
+    let mut report = Vec::with_capacity(lines.len());
+    let mut queue: Vec<&Game> = Vec::new();
+
+    for (line, input) in lines {
+        let (title, status, detail) = match resolve_line(games, &input) {
+            Resolution::Found(game) if queue.iter().any(|g| g.title == game.title) => {
+                (Some(game.clean_title()), BatchStatus::Duplicate, None)
+            }
+            Resolution::Found(game) => {
+                queue.push(game);
+                // Final status is filled in once the queue has run
+                (Some(game.clean_title()), BatchStatus::Failed, None)
+            }
+            Resolution::Ambiguous(candidates) => {
+                let mut detail = candidates
+                    .iter()
+                    .take(MAX_CANDIDATES)
+                    .map(|game| game.clean_title())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                if candidates.len() > MAX_CANDIDATES {
+                    detail.push_str(&format!("; ... {} matches in total", candidates.len()));
+                }
+                (None, BatchStatus::Ambiguous, Some(detail))
+            }
+            Resolution::NoMatch => (None, BatchStatus::NoMatch, None),
+        };
+
+        match status {
+            BatchStatus::Ambiguous => reporter.message(format!("Line {}: '{}' is ambiguous", line, input)),
+            BatchStatus::NoMatch => reporter.message(format!("Line {}: no match for '{}'", line, input)),
+            _ => {}
+        }
+
+        report.push(BatchLine {
+            line,
+            input,
+            title,
+            status,
+            detail,
+        });
+    }
+
+    reporter.message(format!(
+        "Resolved {} of {} lines, downloading {} game(s)",
+        report.iter().filter(|l| l.title.is_some()).count(),
+        report.len(),
+        queue.len()
+    ));
+
+    let results = download_queue(downloader, &queue).await;
+
+    for entry in report.iter_mut() {
+        if entry.status != BatchStatus::Failed {
+            continue;
+        }
+        if let Some(result) = results.iter().find(|r| Some(&r.title) == entry.title.as_ref()) {
+            if result.succeeded() && result.is_plan() {
+                entry.status = BatchStatus::Planned;
+            } else if result.succeeded() {
+                entry.status = BatchStatus::Downloaded;
+            } else {
+                entry.detail = result.error.clone();
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Prints the per-line report of a batch run.
+pub fn print_batch_report(report: &[BatchLine]) {
+    println!("\nBatch report:");
+    for entry in report {
+        let status = match entry.status {
+            BatchStatus::Downloaded => "OK",
+            BatchStatus::Planned => "PLANNED",
+            BatchStatus::Failed => "FAILED",
+            BatchStatus::Ambiguous => "AMBIGUOUS",
+            BatchStatus::NoMatch => "NO MATCH",
+            BatchStatus::Duplicate => "DUPLICATE",
+        };
+        let title = entry
+            .title
+            .as_ref()
+            .filter(|title| **title != entry.input)
+            .map(|title| format!(" -> {}", title))
+            .unwrap_or_default();
+        let detail = entry
+            .detail
+            .as_ref()
+            .map(|detail| format!(" ({})", detail))
+            .unwrap_or_default();
+        println!("  {:>4}: {:<9} {}{}{}", entry.line, status, entry.input, title, detail);
+    }
+    println!();
+}
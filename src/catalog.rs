@@ -0,0 +1,799 @@
+use crate::{
+    config::Config,
+    dat::DatEntry,
+    error::Error,
+    models::{DumpInfo, Game, GameType},
+    listing::Validators,
+    search::{full_text_expression, normalize, SearchKey},
+    utils::unix_now,
+};
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Schema version kept in `PRAGMA user_version`. Bump it and add a step to `migrate`
+/// whenever the tables change, so existing databases are upgraded in place.
+const SCHEMA_VERSION: i64 = 7;
+
+/// Tables of schema version 1.
+const SCHEMA_V1: &str = "
+    -- One row per catalogue refresh (or JSON cache import)
+    CREATE TABLE refreshes (
+        id INTEGER PRIMARY KEY,
+        source TEXT NOT NULL,
+        refreshed_at INTEGER NOT NULL,
+        games INTEGER NOT NULL
+    );
+
+    -- Catalogue entries; list columns hold JSON arrays
+    CREATE TABLE games (
+        id INTEGER PRIMARY KEY,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL UNIQUE,
+        game_id TEXT NOT NULL,
+        link TEXT NOT NULL,
+        size TEXT NOT NULL,
+        size_bytes INTEGER,
+        game_type TEXT NOT NULL,
+        needs_decryption INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        regions TEXT NOT NULL,
+        languages TEXT NOT NULL,
+        revision TEXT,
+        version TEXT,
+        disc INTEGER,
+        flags TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        first_seen INTEGER NOT NULL REFERENCES refreshes(id),
+        last_seen INTEGER NOT NULL REFERENCES refreshes(id)
+    );
+    CREATE INDEX games_position ON games(position);
+    CREATE INDEX games_game_id ON games(game_id);
+
+    -- Normalised title words (see search::normalize), rowid = games.id
+    CREATE VIRTUAL TABLE games_fts USING fts5(words);
+
+    -- Key files listed by the keys server, by game ID
+    CREATE TABLE key_files (
+        game_id TEXT PRIMARY KEY,
+        key_file TEXT NOT NULL,
+        refreshed_at INTEGER NOT NULL
+    );
+
+    -- Games downloaded and decrypted by this tool, by game ID
+    CREATE TABLE downloads (
+        game_id TEXT PRIMARY KEY,
+        iso_path TEXT NOT NULL,
+        downloaded_at INTEGER NOT NULL
+    );
+";
+
+/// Schema version 2: HTTP validators of the fetched listing and when it was last checked,
+/// even if unchanged, for conditional refreshes and the cache TTL.
+const SCHEMA_V2: &str = "
+    ALTER TABLE refreshes ADD COLUMN checked_at INTEGER;
+    ALTER TABLE refreshes ADD COLUMN etag TEXT;
+    ALTER TABLE refreshes ADD COLUMN last_modified TEXT;
+    UPDATE refreshes SET checked_at = refreshed_at;
+";
+
+/// Schema version 3: listing dates, the catalogue as it was before the latest refresh,
+/// and what each refresh changed.
+const SCHEMA_V3: &str = "
+    ALTER TABLE games ADD COLUMN date TEXT;
+
+    CREATE TABLE previous_games (
+        title TEXT PRIMARY KEY,
+        size TEXT NOT NULL,
+        size_bytes INTEGER,
+        date TEXT
+    );
+
+    -- change is 'added', 'removed' or 'resized'
+    CREATE TABLE changes (
+        refresh INTEGER NOT NULL REFERENCES refreshes(id),
+        title TEXT NOT NULL,
+        change TEXT NOT NULL,
+        old_size TEXT,
+        new_size TEXT
+    );
+    CREATE INDEX changes_refresh ON changes(refresh);
+";
+
+/// Schema version 4: the watchlist and the titles it queued for download.
+const SCHEMA_V4: &str = "
+    -- Saved queries (see query::Query) checked on every refresh
+    CREATE TABLE watches (
+        id INTEGER PRIMARY KEY,
+        query TEXT NOT NULL UNIQUE,
+        auto_queue INTEGER NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+    -- Titles queued by watches with auto_queue, until they are downloaded
+    CREATE TABLE download_queue (
+        title TEXT PRIMARY KEY,
+        watch_query TEXT NOT NULL,
+        queued_at INTEGER NOT NULL
+    );
+";
+
+/// Schema version 5: entries of the imported Redump DAT, by game ID.
+const SCHEMA_V5: &str = "
+    CREATE TABLE dat_entries (
+        game_id TEXT PRIMARY KEY,
+        serial TEXT,
+        size INTEGER,
+        crc32 TEXT,
+        md5 TEXT,
+        sha1 TEXT
+    );
+    CREATE INDEX dat_entries_serial ON dat_entries(serial);
+";
+
+/// Schema version 6: hashes of the listed ZIPs, for listings that give them.
+const SCHEMA_V6: &str = "
+    ALTER TABLE games ADD COLUMN zip_md5 TEXT;
+    ALTER TABLE games ADD COLUMN zip_sha1 TEXT;
+";
+
+/// Schema version 7: games of several platforms. Redump reuses titles across platforms, so
+/// titles are unique per game type, and each platform's listing keeps its own validators.
+const SCHEMA_V7: &str = "
+    CREATE TABLE games_v7 (
+        id INTEGER PRIMARY KEY,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        game_id TEXT NOT NULL,
+        link TEXT NOT NULL,
+        size TEXT NOT NULL,
+        size_bytes INTEGER,
+        game_type TEXT NOT NULL,
+        needs_decryption INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        regions TEXT NOT NULL,
+        languages TEXT NOT NULL,
+        revision TEXT,
+        version TEXT,
+        disc INTEGER,
+        flags TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        first_seen INTEGER NOT NULL REFERENCES refreshes(id),
+        last_seen INTEGER NOT NULL REFERENCES refreshes(id),
+        date TEXT,
+        zip_md5 TEXT,
+        zip_sha1 TEXT,
+        UNIQUE (game_type, title)
+    );
+    INSERT INTO games_v7 (id, position, title, game_id, link, size, size_bytes, game_type, needs_decryption,
+        name, regions, languages, revision, version, disc, flags, tags, first_seen, last_seen, date, zip_md5, zip_sha1)
+    SELECT id, position, title, game_id, link, size, size_bytes, game_type, needs_decryption,
+        name, regions, languages, revision, version, disc, flags, tags, first_seen, last_seen, date, zip_md5, zip_sha1
+    FROM games;
+    DROP TABLE games;
+    ALTER TABLE games_v7 RENAME TO games;
+    CREATE INDEX games_position ON games(position);
+    CREATE INDEX games_game_id ON games(game_id);
+
+    CREATE TABLE previous_games_v7 (
+        game_type TEXT NOT NULL,
+        title TEXT NOT NULL,
+        size TEXT NOT NULL,
+        size_bytes INTEGER,
+        date TEXT,
+        PRIMARY KEY (game_type, title)
+    );
+    INSERT INTO previous_games_v7 SELECT 'PS3', title, size, size_bytes, date FROM previous_games;
+    DROP TABLE previous_games;
+    ALTER TABLE previous_games_v7 RENAME TO previous_games;
+
+    ALTER TABLE changes ADD COLUMN game_type TEXT NOT NULL DEFAULT 'PS3';
+
+    -- Where each platform's listing was last fetched from, with the HTTP validators it issued
+    CREATE TABLE listings (
+        game_type TEXT PRIMARY KEY,
+        source TEXT NOT NULL,
+        etag TEXT,
+        last_modified TEXT
+    );
+    INSERT INTO listings (game_type, source, etag, last_modified)
+    SELECT 'PS3', source, etag, last_modified FROM refreshes ORDER BY id DESC LIMIT 1;
+";
+
+/// Migration steps in order; step N brings the schema from version N - 1 to N.
+const MIGRATIONS: [&str; SCHEMA_VERSION as usize] =
+    [SCHEMA_V1, SCHEMA_V2, SCHEMA_V3, SCHEMA_V4, SCHEMA_V5, SCHEMA_V6, SCHEMA_V7];
+
+/// Columns read by `game_from_row`, with the key file and DAT entry joined in.
+const GAME_COLUMNS: &str = "g.position, g.title, g.link, g.size, g.size_bytes, g.game_type, g.needs_decryption,
+     g.name, g.regions, g.languages, g.revision, g.version, g.disc, g.flags, g.tags, k.key_file, g.date,
+     d.serial, d.size, d.crc32, d.md5, d.sha1, g.zip_md5, g.zip_sha1";
+
+/// Joins for `GAME_COLUMNS`, after `games g`.
+const GAME_JOINS: &str = "LEFT JOIN key_files k ON k.game_id = g.game_id LEFT JOIN dat_entries d ON d.game_id = g.game_id";
+
+/// Where one platform's listing was fetched from, with the HTTP validators it issued.
+#[derive(Debug, Clone)]
+pub struct ListingSource {
+    pub game_type: GameType,
+    /// Location of the mirror that answered
+    pub source: String,
+    pub validators: Validators,
+}
+
+/// A saved watch query.
+#[derive(Debug, Clone, Serialize)]
+pub struct Watch {
+    pub id: i64,
+    /// Query text, as accepted by `Query::parse`
+    pub query: String,
+    /// Queue new matches for download once they have a key
+    pub auto_queue: bool,
+}
+
+/// How a title changed between two refreshes of the catalogue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Still listed, with a different size (usually a redump)
+    Resized,
+}
+
+impl ChangeKind {
+    /// Returns the name the change is stored under.
+    fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Resized => "resized",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [ChangeKind::Added, ChangeKind::Removed, ChangeKind::Resized]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+/// One title added, removed or resized by a refresh.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub title: String,
+    pub game_type: GameType,
+    pub kind: ChangeKind,
+    /// Listed size before the refresh, unless the title was added
+    pub old_size: Option<String>,
+    /// Listed size after the refresh, unless the title was removed
+    pub new_size: Option<String>,
+}
+
+/// What one refresh changed compared to the catalogue before it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshDiff {
+    /// Seconds since the Unix epoch
+    pub refreshed_at: u64,
+    pub changes: Vec<Change>,
+}
+
+impl RefreshDiff {
+    /// Counts the changes of one kind.
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|change| change.kind == kind).count()
+    }
+}
+
+/// The catalogue database: games with their parsed fields, a full-text index over their
+/// titles, refresh timestamps, and the key files and downloads known for each game.
+pub struct Catalog {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl Catalog {
+    /// Opens (or creates) the catalogue database next to the downloads, upgrading its schema if needed.
+    pub fn open(config: &Config) -> Result<Self> {
+        let path = config.catalog_db_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::filesystem("create", parent, e))?;
+        }
+        let connection = Connection::open(&path).map_err(|e| Error::database(&path, e))?;
+        let mut catalog = Self { connection, path };
+        catalog.migrate()?;
+        Ok(catalog)
+    }
+
+    /// Brings the schema up to `SCHEMA_VERSION`. Each step runs in its own transaction
+    /// together with the version bump, so a failed step leaves the database as it was.
+    fn migrate(&mut self) -> Result<()> {
+        let version: i64 = self.db(self.connection.query_row("PRAGMA user_version", [], |row| row.get(0)))?;
+        for (index, step) in MIGRATIONS.iter().enumerate() {
+            let step_version = index as i64 + 1;
+            if version < step_version {
+                let result = self.apply_migration(step, step_version);
+                self.db(result)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_migration(&mut self, step: &str, version: i64) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(step)?;
+        transaction.pragma_update(None, "user_version", version)?;
+        transaction.commit()
+    }
+
+    /// Wraps a database error with the database path.
+    fn db<T>(&self, result: rusqlite::Result<T>) -> Result<T> {
+        result.map_err(|e| Error::database(&self.path, e).into())
+    }
+
+    /// Returns the database file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if no catalogue has been stored yet.
+    pub fn is_empty(&self) -> Result<bool> {
+        let count: i64 = self.db(self.connection.query_row("SELECT COUNT(*) FROM games", [], |row| row.get(0)))?;
+        Ok(count == 0)
+    }
+
+    /// Returns when the source was last checked for changes, in seconds since the Unix epoch.
+    pub fn last_checked_at(&self) -> Result<Option<u64>> {
+        let result = self
+            .connection
+            .query_row("SELECT MAX(checked_at) FROM refreshes", [], |row| row.get::<_, Option<i64>>(0));
+        Ok(self.db(result)?.map(|time| time as u64))
+    }
+
+    /// Returns where a platform's listing was last fetched from, with the HTTP validators it issued.
+    pub fn validators(&self, game_type: GameType) -> Result<Option<(String, Validators)>> {
+        let result = self
+            .connection
+            .query_row(
+                "SELECT source, etag, last_modified FROM listings WHERE game_type = ?1",
+                params![game_type.name()],
+                |row| {
+                    let validators = Validators {
+                        etag: row.get(1)?,
+                        last_modified: row.get(2)?,
+                    };
+                    Ok((row.get(0)?, validators))
+                },
+            )
+            .optional();
+        self.db(result)
+    }
+
+    /// Records that the source was checked and had not changed since the latest refresh.
+    pub fn mark_unchanged(&self) -> Result<()> {
+        let result = self.connection.execute(
+            "UPDATE refreshes SET checked_at = ?1 WHERE id = (SELECT MAX(id) FROM refreshes)",
+            params![unix_now() as i64],
+        );
+        self.db(result).map(|_| ())
+    }
+
+    /// Replaces the catalogue with a freshly fetched game list of every listed platform, in
+    /// one transaction. Games keep their first-seen refresh; games no longer listed are removed.
+    /// The replaced catalogue is kept as the previous snapshot, and the titles added, removed
+    /// and resized since are recorded and returned. The first refresh has no diff.
+    pub fn replace_games(&mut self, games: &[Game], listings: &[ListingSource]) -> Result<Option<RefreshDiff>> {
+        let result = self.write_games(games, listings);
+        self.db(result)?;
+        self.latest_diff()
+    }
+
+    fn write_games(&mut self, games: &[Game], listings: &[ListingSource]) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+
+        // Keep the catalogue being replaced as the previous snapshot
+        transaction.execute("DELETE FROM previous_games", [])?;
+        transaction.execute(
+            "INSERT INTO previous_games (game_type, title, size, size_bytes, date)
+             SELECT game_type, title, size, size_bytes, date FROM games",
+            [],
+        )?;
+
+        let sources: Vec<&str> = listings.iter().map(|listing| listing.source.as_str()).collect();
+        transaction.execute(
+            "INSERT INTO refreshes (source, refreshed_at, games, checked_at) VALUES (?1, ?2, ?3, ?2)",
+            params![sources.join(", "), unix_now() as i64, games.len() as i64],
+        )?;
+        let refresh = transaction.last_insert_rowid();
+
+        transaction.execute("DELETE FROM listings", [])?;
+        for listing in listings {
+            transaction.execute(
+                "INSERT INTO listings (game_type, source, etag, last_modified) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    listing.game_type.name(),
+                    listing.source,
+                    listing.validators.etag,
+                    listing.validators.last_modified
+                ],
+            )?;
+        }
+
+        {
+            let mut upsert = transaction.prepare(
+                "INSERT INTO games (position, title, game_id, link, size, size_bytes, game_type, needs_decryption,
+                     name, regions, languages, revision, version, disc, flags, tags, first_seen, last_seen, date,
+                     zip_md5, zip_sha1)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?17, ?18, ?19, ?20)
+                 ON CONFLICT(game_type, title) DO UPDATE SET
+                     position = excluded.position, game_id = excluded.game_id, link = excluded.link,
+                     size = excluded.size, size_bytes = excluded.size_bytes, game_type = excluded.game_type,
+                     needs_decryption = excluded.needs_decryption, name = excluded.name,
+                     regions = excluded.regions, languages = excluded.languages,
+                     revision = excluded.revision, version = excluded.version, disc = excluded.disc,
+                     flags = excluded.flags, tags = excluded.tags, last_seen = excluded.last_seen,
+                     date = excluded.date, zip_md5 = excluded.zip_md5, zip_sha1 = excluded.zip_sha1",
+            )?;
+            for (index, game) in games.iter().enumerate() {
+                upsert.execute(params![
+                    index as i64 + 1,
+                    game.title,
+                    game.get_game_id(),
+                    game.link,
+                    game.size,
+                    game.size_bytes.map(|bytes| bytes as i64),
+                    game.game_type.name(),
+                    game.needs_decryption,
+                    game.name,
+                    to_json(&game.regions),
+                    to_json(&game.languages),
+                    game.revision,
+                    game.version,
+                    game.disc,
+                    to_json(&game.flags),
+                    to_json(&game.tags),
+                    refresh,
+                    game.date,
+                    game.zip_md5,
+                    game.zip_sha1,
+                ])?;
+            }
+        }
+
+        // Diff against the previous snapshot, unless this is the first refresh
+        let previous: i64 = transaction.query_row("SELECT COUNT(*) FROM previous_games", [], |row| row.get(0))?;
+        if previous > 0 {
+            transaction.execute(
+                "INSERT INTO changes (refresh, title, change, old_size, new_size, game_type)
+                 SELECT ?1, g.title, 'added', NULL, g.size, g.game_type FROM games g
+                 WHERE NOT EXISTS (SELECT 1 FROM previous_games p WHERE p.game_type = g.game_type AND p.title = g.title)
+                 UNION ALL
+                 SELECT ?1, p.title, 'removed', p.size, NULL, p.game_type FROM previous_games p
+                 JOIN games g ON g.game_type = p.game_type AND g.title = p.title WHERE g.last_seen != ?1
+                 UNION ALL
+                 SELECT ?1, g.title, 'resized', p.size, g.size, g.game_type FROM games g
+                 JOIN previous_games p ON p.game_type = g.game_type AND p.title = g.title
+                 WHERE g.last_seen = ?1 AND COALESCE(p.size_bytes, p.size) IS NOT COALESCE(g.size_bytes, g.size)",
+                params![refresh],
+            )?;
+        }
+        transaction.execute("DELETE FROM games WHERE last_seen != ?1", params![refresh])?;
+
+        // Rebuild the full-text index from the normalised titles
+        transaction.execute("DELETE FROM games_fts", [])?;
+        {
+            let mut ids = transaction.prepare("SELECT id, title FROM games")?;
+            let rows: Vec<(i64, String)> = ids
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            let mut insert = transaction.prepare("INSERT INTO games_fts (rowid, words) VALUES (?1, ?2)")?;
+            for (id, title) in rows {
+                let title = title.trim_end_matches(".zip");
+                insert.execute(params![id, normalize(title).join(" ")])?;
+            }
+        }
+
+        transaction.commit()
+    }
+
+    /// Returns what the latest refresh changed, or `None` before the second refresh.
+    pub fn latest_diff(&self) -> Result<Option<RefreshDiff>> {
+        let result = (|| {
+            let has_previous: bool = self
+                .connection
+                .query_row("SELECT EXISTS (SELECT 1 FROM previous_games)", [], |row| row.get(0))?;
+            let latest = self
+                .connection
+                .query_row(
+                    "SELECT id, refreshed_at FROM refreshes ORDER BY id DESC LIMIT 1",
+                    [],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .optional()?;
+            match latest.filter(|_| has_previous) {
+                Some((refresh, refreshed_at)) => self.load_diff(refresh, refreshed_at).map(Some),
+                None => Ok(None),
+            }
+        })();
+        self.db(result)
+    }
+
+    /// Returns the refreshes since the given time (seconds since the Unix epoch) that changed
+    /// anything, newest first.
+    pub fn diffs_since(&self, since: u64) -> Result<Vec<RefreshDiff>> {
+        let result = (|| {
+            let mut statement = self.connection.prepare(
+                "SELECT id, refreshed_at FROM refreshes r
+                 WHERE refreshed_at >= ?1 AND EXISTS (SELECT 1 FROM changes c WHERE c.refresh = r.id)
+                 ORDER BY id DESC",
+            )?;
+            let refreshes = statement
+                .query_map(params![since as i64], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            refreshes
+                .into_iter()
+                .map(|(refresh, refreshed_at)| self.load_diff(refresh, refreshed_at))
+                .collect()
+        })();
+        self.db(result)
+    }
+
+    fn load_diff(&self, refresh: i64, refreshed_at: i64) -> rusqlite::Result<RefreshDiff> {
+        let mut statement = self.connection.prepare(
+            "SELECT title, change, old_size, new_size, game_type FROM changes WHERE refresh = ?1
+             ORDER BY change, title",
+        )?;
+        let changes = statement
+            .query_map(params![refresh], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .filter_map(|(title, change, old_size, new_size, game_type)| {
+                Some(Change {
+                    title,
+                    game_type: GameType::from_name(&game_type).unwrap_or(GameType::PS3),
+                    kind: ChangeKind::from_name(&change)?,
+                    old_size,
+                    new_size,
+                })
+            })
+            .collect();
+        Ok(RefreshDiff {
+            refreshed_at: refreshed_at as u64,
+            changes,
+        })
+    }
+
+    /// Loads the whole catalogue in catalogue order.
+    pub fn load_games(&self) -> Result<Vec<Game>> {
+        let sql = format!("SELECT {} FROM games g {} ORDER BY g.position", GAME_COLUMNS, GAME_JOINS);
+        let result = self.query_games(&sql, [])
+            .map(|games| games.into_iter().map(|(_, game)| game).collect());
+        self.db(result)
+    }
+
+    /// Looks the search words up in the full-text index, as word prefixes that must all appear
+    /// (see `full_text_expression`). Returns the matching games with their 1-based catalogue
+    /// index, in catalogue order; none if the index cannot answer the search.
+    pub fn full_text_search(&self, search: &str) -> Result<Vec<(usize, Game)>> {
+        let Some(expression) = full_text_expression(search) else {
+            return Ok(Vec::new());
+        };
+        let sql = format!(
+            "SELECT {} FROM games_fts f JOIN games g ON g.id = f.rowid {}
+             WHERE games_fts MATCH ?1 ORDER BY g.position",
+            GAME_COLUMNS, GAME_JOINS
+        );
+        let result = self.query_games(&sql, [expression]);
+        self.db(result)
+    }
+
+    fn query_games<P: rusqlite::Params>(&self, sql: &str, params: P) -> rusqlite::Result<Vec<(usize, Game)>> {
+        let mut statement = self.connection.prepare(sql)?;
+        let games = statement
+            .query_map(params, game_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(games)
+    }
+
+    /// Replaces the key files known for each game ID. Returns the game IDs that had no key
+    /// file before, or none the first time keys are stored.
+    pub fn store_key_files(&mut self, keys: &HashMap<String, String>) -> Result<Vec<String>> {
+        let result = (|| {
+            let transaction = self.connection.transaction()?;
+            let known: HashSet<String> = transaction
+                .prepare("SELECT game_id FROM key_files")?
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            let added: Vec<String> = match known.is_empty() {
+                true => Vec::new(),
+                false => keys.keys().filter(|game_id| !known.contains(*game_id)).cloned().collect(),
+            };
+
+            transaction.execute("DELETE FROM key_files", [])?;
+            {
+                let mut insert = transaction
+                    .prepare("INSERT INTO key_files (game_id, key_file, refreshed_at) VALUES (?1, ?2, ?3)")?;
+                let now = unix_now() as i64;
+                for (game_id, key_file) in keys {
+                    insert.execute(params![game_id, key_file, now])?;
+                }
+            }
+            transaction.commit()?;
+            Ok(added)
+        })();
+        self.db(result)
+    }
+
+    /// Replaces the imported DAT entries. Returns how many of them match a catalogue title.
+    pub fn replace_dat_entries(&mut self, entries: &[DatEntry]) -> Result<usize> {
+        let result = (|| {
+            let transaction = self.connection.transaction()?;
+            transaction.execute("DELETE FROM dat_entries", [])?;
+            {
+                let mut insert = transaction.prepare(
+                    "INSERT INTO dat_entries (game_id, serial, size, crc32, md5, sha1) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT(game_id) DO NOTHING",
+                )?;
+                for entry in entries {
+                    let dump = entry.dump.as_ref();
+                    insert.execute(params![
+                        entry.name,
+                        entry.serial,
+                        dump.map(|dump| dump.size as i64),
+                        dump.and_then(|dump| dump.crc32.as_deref()),
+                        dump.and_then(|dump| dump.md5.as_deref()),
+                        dump.and_then(|dump| dump.sha1.as_deref()),
+                    ])?;
+                }
+            }
+            let matched: i64 = transaction.query_row(
+                "SELECT COUNT(*) FROM dat_entries d JOIN games g ON g.game_id = d.game_id",
+                [],
+                |row| row.get(0),
+            )?;
+            transaction.commit()?;
+            Ok(matched as usize)
+        })();
+        self.db(result)
+    }
+
+    /// Saves a watch query. Returns false if the same query is already watched.
+    pub fn add_watch(&self, query: &str, auto_queue: bool) -> Result<bool> {
+        let result = self.connection.execute(
+            "INSERT INTO watches (query, auto_queue, created_at) VALUES (?1, ?2, ?3) ON CONFLICT(query) DO NOTHING",
+            params![query, auto_queue, unix_now() as i64],
+        );
+        Ok(self.db(result)? > 0)
+    }
+
+    /// Returns the watchlist in the order it was saved.
+    pub fn watches(&self) -> Result<Vec<Watch>> {
+        let result = (|| {
+            let mut statement = self
+                .connection
+                .prepare("SELECT id, query, auto_queue FROM watches ORDER BY id")?;
+            let watches = statement
+                .query_map([], |row| {
+                    Ok(Watch {
+                        id: row.get(0)?,
+                        query: row.get(1)?,
+                        auto_queue: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(watches)
+        })();
+        self.db(result)
+    }
+
+    /// Removes a watch by ID. Returns false if there is none.
+    pub fn remove_watch(&self, id: i64) -> Result<bool> {
+        let result = self.connection.execute("DELETE FROM watches WHERE id = ?1", params![id]);
+        Ok(self.db(result)? > 0)
+    }
+
+    /// Queues a title for download on behalf of a watch. Returns false if it is already queued.
+    pub fn queue_download(&self, title: &str, watch_query: &str) -> Result<bool> {
+        let result = self.connection.execute(
+            "INSERT INTO download_queue (title, watch_query, queued_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(title) DO NOTHING",
+            params![title, watch_query, unix_now() as i64],
+        );
+        Ok(self.db(result)? > 0)
+    }
+
+    /// Returns the queued titles, oldest first.
+    pub fn queued_downloads(&self) -> Result<Vec<String>> {
+        let result = (|| {
+            let mut statement = self
+                .connection
+                .prepare("SELECT title FROM download_queue ORDER BY queued_at, title")?;
+            let titles = statement
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(titles)
+        })();
+        self.db(result)
+    }
+
+    /// Takes a title off the download queue.
+    pub fn dequeue_download(&self, title: &str) -> Result<()> {
+        let result = self
+            .connection
+            .execute("DELETE FROM download_queue WHERE title = ?1", params![title]);
+        self.db(result).map(|_| ())
+    }
+
+    /// Records that a game was downloaded and decrypted to `iso_path`.
+    pub fn record_download(&self, game: &Game, iso_path: &Path) -> Result<()> {
+        let result = self.connection.execute(
+            "INSERT INTO downloads (game_id, iso_path, downloaded_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(game_id) DO UPDATE SET iso_path = excluded.iso_path, downloaded_at = excluded.downloaded_at",
+            params![game.get_game_id(), iso_path.to_string_lossy(), unix_now() as i64],
+        );
+        self.db(result).map(|_| ())
+    }
+}
+
+/// Builds a game from the columns listed in `GAME_COLUMNS`, with its catalogue index.
+fn game_from_row(row: &Row) -> rusqlite::Result<(usize, Game)> {
+    let position: i64 = row.get(0)?;
+    let dump = match row.get::<_, Option<i64>>(18)? {
+        Some(size) => Some(DumpInfo {
+            size: size as u64,
+            crc32: row.get(19)?,
+            md5: row.get(20)?,
+            sha1: row.get(21)?,
+        }),
+        None => None,
+    };
+    let game = Game {
+        title: row.get(1)?,
+        link: row.get(2)?,
+        size: row.get(3)?,
+        size_bytes: row.get::<_, Option<i64>>(4)?.map(|bytes| bytes as u64),
+        lowercased_title: String::new(),
+        search_key: SearchKey::default(),
+        game_type: GameType::from_name(&row.get::<_, String>(5)?).unwrap_or(GameType::PS3),
+        needs_decryption: row.get(6)?,
+        region: None,
+        name: row.get(7)?,
+        regions: from_json(&row.get::<_, String>(8)?),
+        languages: from_json(&row.get::<_, String>(9)?),
+        revision: row.get(10)?,
+        version: row.get(11)?,
+        disc: row.get(12)?,
+        flags: from_json(&row.get::<_, String>(13)?),
+        tags: from_json(&row.get::<_, String>(14)?),
+        key_file: row.get(15)?,
+        date: row.get(16)?,
+        serial: row.get(17)?,
+        dump,
+        zip_md5: row.get(22)?,
+        zip_sha1: row.get(23)?,
+        key: None,
+    };
+    let mut game = game.with_lowercased();
+    if !game.regions.is_empty() {
+        game.region = Some(game.regions.join(", "));
+    }
+    Ok((position as usize, game))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn from_json<T: serde::de::DeserializeOwned + Default>(value: &str) -> T {
+    serde_json::from_str(value).unwrap_or_default()
+}
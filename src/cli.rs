@@ -0,0 +1,139 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Command-line arguments. Without a subcommand the interactive prompt is started.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "ps3-redump-downloader", version, about = "Search, download and decrypt PS3 Redump ISOs")]
+pub struct Cli {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Options shared by all subcommands.
+#[derive(Debug, Clone, Default, Args)]
+pub struct GlobalOptions {
+    /// Print machine-readable JSON on stdout; status messages and progress go to stderr
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Write progress events as NDJSON to `stdout`, `stderr`, `fd:N` or a file path
+    #[arg(long, global = true, value_name = "TARGET")]
+    pub progress_events: Option<String>,
+    /// Only plan downloads: look up the key, probe the remote size, check the decryptor
+    /// and print every path that would be created or deleted, then stop
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Check the game list and keys list for changes now, even if their caches have not expired
+    #[arg(long, global = true)]
+    pub refresh: bool,
+    /// Never touch the network: use only the caches and sources on a local or mounted disk
+    #[arg(long, global = true)]
+    pub offline: bool,
+}
+
+/// Non-interactive subcommands, meant for scripts and cron jobs.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Print catalogue entries matching a search, best matches first
+    Search {
+        /// Search words and filters, e.g. `final fantasy region:usa size<8GB -demo sort:size`
+        #[arg(required = true, allow_hyphen_values = true)]
+        terms: Vec<String>,
+    },
+    /// Download, extract and decrypt a game
+    Download {
+        /// Exact Redump title (with or without .zip) or catalogue index as printed by `list`/`search`
+        target: String,
+    },
+    /// Download every game listed in a file, one exact title, search or title ID per line
+    Batch {
+        /// Text file with one entry per line; empty lines and lines starting with # are ignored
+        file: PathBuf,
+        /// Also write the per-line report as JSON to this file
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+    /// Print the whole catalogue with its indexes, or only the entries matching filters
+    List {
+        /// Optional search words, filters and sort order, e.g. `region:japan has:key sort:-size`
+        #[arg(allow_hyphen_values = true)]
+        filters: Vec<String>,
+    },
+    /// Keep one release per game, by region and language preference (1G1R, one game one ROM)
+    #[command(name = "1g1r")]
+    OneGameOneRom {
+        /// Optional search words, filters and sort order applied before picking, e.g. `-region:japan`
+        #[arg(allow_hyphen_values = true)]
+        filters: Vec<String>,
+        /// Region preference, most wanted first, overriding REGIONS in config.ini (e.g. "Europe,USA,Japan")
+        #[arg(long, value_name = "LIST")]
+        regions: Option<String>,
+        /// Language preference, most wanted first, overriding LANGUAGES in config.ini (e.g. "En,Fr")
+        #[arg(long, value_name = "LIST")]
+        languages: Option<String>,
+        /// Skip games with none of the preferred languages
+        #[arg(long)]
+        require_language: bool,
+        /// Write the picked titles to this file, one per line, for the `batch` command
+        #[arg(long, value_name = "PATH")]
+        export: Option<PathBuf>,
+        /// Download the picked titles one after another
+        #[arg(long)]
+        download: bool,
+    },
+    /// Show what recent catalogue refreshes added, removed or resized, and the newest dumps by date
+    #[command(name = "whats-new")]
+    WhatsNew {
+        /// How many days back to look
+        #[arg(long, default_value_t = 7)]
+        days: u64,
+    },
+    /// Import a Redump DAT file, adding serials, exact ISO sizes and hashes to the catalogue
+    ImportDat {
+        /// Logiqx XML DAT, e.g. "Sony - PlayStation 3 - Datfile (4000) (2024-01-01).dat"
+        path: PathBuf,
+    },
+    /// Save queries that are reported when matching titles or their keys appear
+    Watch {
+        #[command(subcommand)]
+        action: WatchAction,
+    },
+    /// Fetch the game list from Redump again and overwrite the cache
+    RefreshCatalog,
+    /// Fetch the decryption keys list again and overwrite the cache
+    RefreshKeys,
+    /// Browse the catalogue in a full-screen terminal UI
+    Tui,
+}
+
+/// Watchlist actions.
+#[derive(Debug, Clone, Subcommand)]
+pub enum WatchAction {
+    /// Save a query, e.g. `watch add --auto-queue "demon's souls" region:japan`
+    Add {
+        /// Queue new matches for download once they have a key
+        #[arg(long)]
+        auto_queue: bool,
+        /// Search words and filters, as for `search`
+        #[arg(required = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Print the saved queries with their IDs, and the titles queued for download
+    List,
+    /// Remove a saved query by ID
+    Remove {
+        id: i64,
+    },
+    /// Check the game list and keys list for changes now and report new matches
+    Check,
+    /// Download the titles the watchlist queued
+    Download,
+}
+
+impl GlobalOptions {
+    /// Returns true if stdout is reserved for machine-readable output.
+    pub fn machine_stdout(&self) -> bool {
+        self.json || matches!(self.progress_events.as_deref(), Some("stdout" | "-"))
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{
+    batch::{print_batch_report, run_batch, BatchStatus},
+    catalog::Catalog,
+    cli::{Command, GlobalOptions, WatchAction},
+    config::Config,
+    dat::read_dat,
+    display::{
+        print_indexed_games, print_indexed_games_json, print_json, print_search_matches_json, print_whats_new,
+        print_whats_new_json, ReportedError,
+    },
+    downloader::Downloader,
+    error::Error,
+    key_manager::KeyManager,
+    one_game_one_rom::{select_one_per_game, Preferences},
+    progress::Reporter,
+    queue::{download_queue, print_queue_summary},
+    scraper::Scraper,
+    query::{query_candidates, query_games, Query},
+    search::find_game,
+    source::Mirrors,
+    tui::run_tui,
+    utils::{format_timestamp, unix_now},
+};
+use anyhow::Result;
+
+/// Builds the reporter for the given output options.
+pub fn build_reporter(options: &GlobalOptions) -> Result<Reporter> {
+    let reporter = if options.json {
+        Reporter::stderr()
+    } else {
+        Reporter::console()
+    };
+
+    match options.progress_events.as_deref() {
+        Some("stdout" | "-") if options.json => {
+            anyhow::bail!("--progress-events stdout cannot be combined with --json; use stderr, fd:N or a file")
+        }
+        Some(target) => reporter.with_event_stream(target),
+        None => Ok(reporter),
+    }
+}
+
+/// Checks the keys list for changes now when `--refresh` is given. The game list is
+/// refreshed by the scraper itself; a failure here only leaves the cached keys in use.
+/// Offline with only web mirrors, there is nothing to check.
+pub async fn refresh_keys_if_requested(config: &Config, options: &GlobalOptions, reporter: &Reporter) {
+    if !options.refresh || Mirrors::keys(config).is_empty() {
+        return;
+    }
+    let mut key_manager = KeyManager::new(config).with_reporter(reporter.clone());
+    if options.dry_run {
+        key_manager = key_manager.without_cache_writes();
+    }
+    if let Err(e) = key_manager.refresh_keys_list().await {
+        tracing::warn!("Could not refresh the PS3 keys list, using the cached one: {:#}", e);
+    }
+}
+
+/// Runs a non-interactive subcommand.
+pub async fn run_command(command: Command, config: &Config, options: &GlobalOptions) -> Result<()> {
+    let reporter = build_reporter(options)?;
+    let scraper = Scraper::new(config)
+        .with_reporter(reporter.clone())
+        .with_force_refresh(options.refresh);
+    // The refresh commands fetch their own list anyway
+    let refreshes_keys = matches!(
+        command,
+        Command::RefreshKeys | Command::Watch { action: WatchAction::Check }
+    );
+    if !refreshes_keys && !matches!(command, Command::RefreshCatalog) {
+        refresh_keys_if_requested(config, options, &reporter).await;
+    }
+
+    match command {
+        Command::Search { terms } => {
+            let query = Query::parse(&terms.join(" "))?;
+
+            // Titles holding the search words as typed are taken from the full-text index, which
+            // spares loading the whole catalogue; anything else (typos, abbreviations, filters
+            // only) is matched over all games
+            let games = match scraper.full_text_candidates(&query.text)? {
+                Some(candidates) => candidates,
+                None => scraper
+                    .get_ps3_list()
+                    .await?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, game)| (i + 1, game))
+                    .collect(),
+            };
+            let games: Vec<_> = games.iter().map(|(index, game)| (*index, game)).collect();
+            let matches = query_candidates(config, &games, &query);
+            if options.json {
+                print_search_matches_json(&matches)?;
+            } else if matches.is_empty() {
+                println!("No games found");
+            } else {
+                let results: Vec<_> = matches.iter().map(|hit| (hit.index, hit.game)).collect();
+                print_indexed_games(&results);
+            }
+        }
+        Command::Download { target } => {
+            let games = scraper.get_ps3_list().await?;
+            let game = match find_game(&games, &target) {
+                Some(game) => game,
+                None => anyhow::bail!("No PS3 game matches '{}'. Use `search` to find the exact title or index.", target),
+            };
+            let report = Downloader::new(config)
+                .with_reporter(reporter)
+                .with_dry_run(options.dry_run)
+                .download_ps3_element(game)
+                .await?;
+            let problems = report.plan.as_ref().map_or(0, |plan| plan.problems.len());
+            if options.json {
+                print_json(&serde_json::json!({ "ok": problems == 0, "result": report }))?;
+            }
+            if problems > 0 {
+                return Err(ReportedError(format!("Dry run found {} problem(s)", problems)).into());
+            }
+        }
+        Command::Batch { file, report } => {
+            let games = scraper.get_ps3_list().await?;
+            let downloader = Downloader::new(config)
+                .with_reporter(reporter)
+                .with_dry_run(options.dry_run);
+            let lines = run_batch(&downloader, &games, &file).await?;
+
+            let failed = lines
+                .iter()
+                .filter(|l| {
+                    !matches!(
+                        l.status,
+                        BatchStatus::Downloaded | BatchStatus::Planned | BatchStatus::Duplicate
+                    )
+                })
+                .count();
+
+            if options.json {
+                print_json(&serde_json::json!({ "ok": failed == 0, "lines": lines }))?;
+            } else {
+                print_batch_report(&lines);
+            }
+            if let Some(path) = report {
+                std::fs::write(&path, serde_json::to_string_pretty(&lines)?)
+                    .map_err(|e| Error::filesystem("write report", &path, e))?;
+            }
+
+            if failed > 0 {
+                let outcome = if options.dry_run { "would not download" } else { "were not downloaded" };
+                let message = format!("{} of {} batch lines {}", failed, lines.len(), outcome);
+                return Err(ReportedError(message).into());
+            }
+        }
+        Command::List { filters } => {
+            let games = scraper.get_ps3_list().await?;
+            let listed: Vec<_> = query_games(config, &games, &filters.join(" "))?
+                .into_iter()
+                .map(|hit| (hit.index, hit.game))
+                .collect();
+            if options.json {
+                print_indexed_games_json(&listed)?;
+            } else {
+                print_indexed_games(&listed);
+            }
+        }
+        Command::OneGameOneRom {
+            filters,
+            regions,
+            languages,
+            require_language,
+            export,
+            download,
+        } => {
+            let games = scraper.get_ps3_list().await?;
+            let candidates: Vec<_> = query_games(config, &games, &filters.join(" "))?
+                .into_iter()
+                .map(|hit| (hit.index, hit.game))
+                .collect();
+            let mut preferences = Preferences::new(config);
+            if let Some(regions) = regions {
+                preferences.regions = Config::parse_priority(&regions);
+            }
+            if let Some(languages) = languages {
+                preferences.languages = Config::parse_priority(&languages);
+            }
+            preferences.require_language |= require_language;
+
+            let picks = select_one_per_game(&candidates, &preferences);
+            let picked: Vec<_> = picks.iter().map(|pick| (pick.index, pick.game)).collect();
+            reporter.message(format!(
+                "Kept {} of {} releases, one per game",
+                picks.len(),
+                candidates.len()
+            ));
+
+            if let Some(path) = &export {
+                let lines: String = picks.iter().map(|pick| format!("{}\n", pick.game.clean_title())).collect();
+                std::fs::write(path, lines).map_err(|e| Error::filesystem("write", path, e))?;
+                reporter.message(format!("Wrote {} titles to {}", picks.len(), path.display()));
+            }
+
+            if !download {
+                if options.json {
+                    print_indexed_games_json(&picked)?;
+                } else {
+                    print_indexed_games(&picked);
+                }
+                return Ok(());
+            }
+
+            let downloader = Downloader::new(config)
+                .with_reporter(reporter)
+                .with_dry_run(options.dry_run);
+            let queue: Vec<_> = picks.iter().map(|pick| pick.game).collect();
+            let results = download_queue(&downloader, &queue).await;
+            let failed = results.iter().filter(|result| !result.succeeded()).count();
+            if options.json {
+                print_json(&serde_json::json!({ "ok": failed == 0, "results": results }))?;
+            } else {
+                print_queue_summary(&results);
+            }
+            if failed > 0 {
+                let outcome = if options.dry_run { "would not download" } else { "were not downloaded" };
+                let message = format!("{} of {} games {}", failed, results.len(), outcome);
+                return Err(ReportedError(message).into());
+            }
+        }
+        Command::WhatsNew { days } => {
+            // Refreshes the list first if its cache has expired, so the diff is current
+            let games = scraper.get_ps3_list().await?;
+            let since = unix_now().saturating_sub(days.saturating_mul(86_400));
+            let diffs = Catalog::open(config)?.diffs_since(since)?;
+
+            // Listing dates have no time zone; compare whole days
+            let first_day = format_timestamp(since)[..10].to_string();
+            let mut recent: Vec<_> = games
+                .iter()
+                .enumerate()
+                .filter(|(_, game)| game.date.as_deref().is_some_and(|date| date >= first_day.as_str()))
+                .map(|(i, game)| (i + 1, game))
+                .collect();
+            recent.sort_by(|(_, a), (_, b)| b.date.cmp(&a.date));
+
+            if options.json {
+                print_whats_new_json(&diffs, &recent)?;
+            } else {
+                print_whats_new(&diffs, &recent, days);
+            }
+        }
+        Command::ImportDat { path } => {
+            let entries = read_dat(&path)?;
+            // Entries are joined to the catalogue by title, so make sure there is one
+            scraper.get_ps3_list().await?;
+            let matched = Catalog::open(config)?.replace_dat_entries(&entries)?;
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true, "entries": entries.len(), "matched": matched }))?;
+            } else {
+                reporter.message(format!(
+                    "Imported {} DAT entries, {} of them match catalogue titles",
+                    entries.len(),
+                    matched
+                ));
+            }
+        }
+        Command::Watch { action } => run_watch_action(action, config, options, &scraper, reporter).await?,
+        Command::RefreshCatalog => {
+            let games = scraper.refresh_ps3_list().await?;
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true, "games": games.len() }))?;
+            }
+        }
+        Command::RefreshKeys => {
+            let keys = KeyManager::new(config)
+                .with_reporter(reporter)
+                .refresh_keys_list()
+                .await?;
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true, "keys": keys.len() }))?;
+            }
+        }
+        Command::Tui => {
+            if options.dry_run {
+                anyhow::bail!("--dry-run is not supported by the full-screen browser");
+            }
+            let games = scraper.get_ps3_list().await?;
+            run_tui(config, games).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a `watch` subcommand.
+async fn run_watch_action(
+    action: WatchAction,
+    config: &Config,
+    options: &GlobalOptions,
+    scraper: &Scraper,
+    reporter: Reporter,
+) -> Result<()> {
+    let catalog = Catalog::open(config)?;
+    match action {
+        WatchAction::Add { auto_queue, query } => {
+            let query = query.join(" ");
+            // Reject typos now rather than on every refresh
+            Query::parse(&query)?;
+            let added = catalog.add_watch(&query, auto_queue)?;
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true, "added": added }))?;
+            } else if added {
+                println!("Watching '{}'", query);
+            } else {
+                println!("'{}' is already watched", query);
+            }
+        }
+        WatchAction::List => {
+            let watches = catalog.watches()?;
+            let queued = catalog.queued_downloads()?;
+            if options.json {
+                print_json(&serde_json::json!({ "watches": watches, "queued": queued }))?;
+                return Ok(());
+            }
+            if watches.is_empty() {
+                println!("The watchlist is empty; add a query with `watch add`");
+            }
+            for watch in &watches {
+                let auto_queue = if watch.auto_queue { " (auto-queue)" } else { "" };
+                println!("{}. {}{}", watch.id, watch.query, auto_queue);
+            }
+            if !queued.is_empty() {
+                println!("\nQueued for download:");
+                for title in &queued {
+                    println!("  {}", title.trim_end_matches(".zip"));
+                }
+            }
+        }
+        WatchAction::Remove { id } => {
+            if !catalog.remove_watch(id)? {
+                anyhow::bail!("No watch with ID {}; see `watch list`", id);
+            }
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true }))?;
+            } else {
+                println!("Removed watch {}", id);
+            }
+        }
+        WatchAction::Check => {
+            // Both refreshes report new matches as they go
+            scraper.refresh_ps3_list().await?;
+            let mut key_manager = KeyManager::new(config).with_reporter(reporter);
+            if options.dry_run {
+                key_manager = key_manager.without_cache_writes();
+            }
+            key_manager.refresh_keys_list().await?;
+            let queued = catalog.queued_downloads()?.len();
+            if options.json {
+                print_json(&serde_json::json!({ "ok": true, "queued": queued }))?;
+            } else if queued > 0 {
+                let noun = if queued == 1 { "title is" } else { "titles are" };
+                println!("{} {} queued for download, run `watch download`", queued, noun);
+            }
+        }
+        WatchAction::Download => {
+            let games = scraper.get_ps3_list().await?;
+            let mut queue = Vec::new();
+            for title in catalog.queued_downloads()? {
+                match games.iter().find(|game| game.title == title) {
+                    Some(game) => queue.push(game),
+                    None => reporter.message(format!(
+                        "Skipping {}: no longer in the game list",
+                        title.trim_end_matches(".zip")
+                    )),
+                }
+            }
+            if queue.is_empty() {
+                reporter.message("Nothing is queued for download");
+                return Ok(());
+            }
+
+            let downloader = Downloader::new(config)
+                .with_reporter(reporter)
+                .with_dry_run(options.dry_run);
+            let results = download_queue(&downloader, &queue).await;
+            for (game, result) in queue.iter().zip(&results) {
+                if result.succeeded() && !result.is_plan() {
+                    catalog.dequeue_download(&game.title)?;
+                }
+            }
+            let failed = results.iter().filter(|result| !result.succeeded()).count();
+            if options.json {
+                print_json(&serde_json::json!({ "ok": failed == 0, "results": results }))?;
+            } else {
+                print_queue_summary(&results);
+            }
+            if failed > 0 {
+                let outcome = if options.dry_run { "would not download" } else { "were not downloaded" };
+                let message = format!("{} of {} games {}", failed, results.len(), outcome);
+                return Err(ReportedError(message).into());
+            }
+        }
+    }
+    Ok(())
+}
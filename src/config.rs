@@ -1,3 +1,4 @@
+use crate::models::GameType;
 use anyhow::Result;
 use configparser::ini::Ini;
 use serde::{Deserialize, Serialize};
@@ -9,6 +10,14 @@ pub struct Config {
     pub ps3_iso_url: String,
     /// Base URL for PS3 decryption keys
     pub ps3_keys_url: String,
+    /// Mirrors of the PS3 ISO listing, tried in order when `ps3_iso_url` fails
+    pub ps3_iso_mirrors: Vec<String>,
+    /// Mirrors of the PS3 keys listing, tried in order when `ps3_keys_url` fails
+    pub ps3_keys_mirrors: Vec<String>,
+    /// Listing URLs of the other platforms (`PS1_ISO`, `PS2_ISO`, `PSP_ISO`), each followed
+    /// by its mirrors. Platforms without one are not listed.
+    #[serde(default)]
+    pub platform_iso_urls: Vec<(GameType, Vec<String>)>,
     /// Name of the JSON file containing the list of PS3 games
     pub list_ps3_files_json_name: String,
     /// Whether to use external browser for ISO download
@@ -19,6 +28,8 @@ pub struct Config {
     pub delay_between_retries: u64,
     /// Timeout for requests (seconds)
     pub timeout_request: Option<u64>,
+    /// How long the game list and keys list caches are used before checking for changes (hours, 0 = forever)
+    pub cache_ttl_hours: u64,
     /// Name of the temporary folder
     pub tmp_folder_name: String,
     /// Name of the ISO folder inside the temporary folder
@@ -27,6 +38,15 @@ pub struct Config {
     pub decryptor_path: String,
     /// Timeout for decryption process (seconds)
     pub decryption_timeout: u64,
+    /// 1G1R region preference, most wanted first
+    pub region_priority: Vec<String>,
+    /// 1G1R language preference, most wanted first
+    pub language_priority: Vec<String>,
+    /// Whether 1G1R skips games with none of the preferred languages
+    pub require_language: bool,
+    /// Never touch the network: only caches and local sources are used (set by `--offline`)
+    #[serde(default)]
+    pub offline: bool,
 }
 
 impl Config {
@@ -37,31 +57,55 @@ impl Config {
 
         let ps3_url_section = config.get("url", "PS3_ISO").map_or("https://myrient.erista.me/files/Redump/Sony%20-%20PlayStation%203/".to_string(), |s| s.to_string());
         let ps3_keys_url = config.get("url", "PS3_KEYS").map_or("https://myrient.erista.me/files/Redump/Sony%20-%20PlayStation%203%20-%20Disc%20Keys%20TXT/".to_string(), |s| s.to_string());
+        let ps3_iso_mirrors = Self::parse_urls(&config.get("url", "PS3_ISO_MIRRORS").unwrap_or_default());
+        let ps3_keys_mirrors = Self::parse_urls(&config.get("url", "PS3_KEYS_MIRRORS").unwrap_or_default());
+        let platform_iso_urls = GameType::ALL
+            .into_iter()
+            .filter(|game_type| *game_type != GameType::PS3)
+            .map(|game_type| {
+                let key = format!("{}_ISO", game_type.name());
+                (game_type, Self::parse_urls(&config.get("url", &key).unwrap_or_default()))
+            })
+            .filter(|(_, urls)| !urls.is_empty())
+            .collect();
         
         let list_ps3_files_json_name = config.get("Download", "LIST_PS3_FILES_JSON_NAME").map_or("listPS3Titles.json".to_string(), |s| s.to_string());
         let external_iso_download = config.getuint("Download", "EXTERNAL_ISO").unwrap_or(Some(0)).unwrap_or(0) != 0;
         let max_retries = config.getuint("Download", "MAX_RETRIES").unwrap_or(Some(5)).unwrap_or(5) as u32;
-        let delay_between_retries = config.getuint("Download", "DELAY_BETWEEN_RETRIES").unwrap_or(Some(5)).unwrap_or(5) as u64;
-        let timeout_request = config.getuint("Download", "TIMEOUT_REQUEST").unwrap_or(None).map(|v| v as u64);
+        let delay_between_retries = config.getuint("Download", "DELAY_BETWEEN_RETRIES").unwrap_or(Some(5)).unwrap_or(5);
+        let timeout_request = config.getuint("Download", "TIMEOUT_REQUEST").unwrap_or(None);
+        let cache_ttl_hours = config.getuint("Download", "CACHE_TTL_HOURS").unwrap_or(Some(24)).unwrap_or(24);
         
         let tmp_folder_name = config.get("folder", "TMP_FOLDER_NAME").map_or("~/PS3-Games".to_string(), |s| s.to_string());
         let tmp_iso_folder_name = config.get("folder", "TMP_ISO_FOLDER_NAME").map_or("iso_files".to_string(), |s| s.to_string());
 
         let decryptor_path = config.get("PS3", "DECRYPTOR_PATH").map_or("./ps3_decryptor".to_string(), |s| s.to_string());
-        let decryption_timeout = config.getuint("PS3", "DECRYPTION_TIMEOUT").unwrap_or(Some(300)).unwrap_or(300) as u64;
+        let decryption_timeout = config.getuint("PS3", "DECRYPTION_TIMEOUT").unwrap_or(Some(300)).unwrap_or(300);
+
+        let region_priority = Self::parse_priority(&config.get("1g1r", "REGIONS").unwrap_or("Europe, USA, World, Japan".to_string()));
+        let language_priority = Self::parse_priority(&config.get("1g1r", "LANGUAGES").unwrap_or("En".to_string()));
+        let require_language = config.getuint("1g1r", "REQUIRE_LANGUAGE").unwrap_or(Some(0)).unwrap_or(0) != 0;
 
         let config = Config {
             ps3_iso_url: ps3_url_section,
             ps3_keys_url,
+            ps3_iso_mirrors,
+            ps3_keys_mirrors,
+            platform_iso_urls,
             list_ps3_files_json_name,
             external_iso_download,
             max_retries,
             delay_between_retries,
             timeout_request,
+            cache_ttl_hours,
             tmp_folder_name,
             tmp_iso_folder_name,
             decryptor_path,
             decryption_timeout,
+            region_priority,
+            language_priority,
+            require_language,
+            offline: false,
         };
 
         // Validate configuration
@@ -89,20 +133,15 @@ impl Config {
         // 1. Current directory
         candidates.push(PathBuf::from("config.ini"));
 
-        #[cfg(windows)]
-        {
-            if let Some(appdata) = std::env::var_os("APPDATA") {
-                candidates.push(PathBuf::from(appdata).join("ps3-redump-downloader/config.ini"));
-            }
-            candidates.push(PathBuf::from("C:/ProgramData/ps3-redump-downloader/config.ini"));
+        // 2. Per-user config folder
+        if let Some(dir) = Self::user_config_dir() {
+            candidates.push(dir.join("config.ini"));
         }
+        // 3. System-wide config folder
+        #[cfg(windows)]
+        candidates.push(PathBuf::from("C:/ProgramData/ps3-redump-downloader/config.ini"));
         #[cfg(not(windows))]
-        {
-            if let Some(home) = std::env::var_os("HOME") {
-                candidates.push(PathBuf::from(home).join(".config/ps3-redump-downloader/config.ini"));
-            }
-            candidates.push(PathBuf::from("/etc/ps3-redump-downloader/config.ini"));
-        }
+        candidates.push(PathBuf::from("/etc/ps3-redump-downloader/config.ini"));
 
         // Try to load from each candidate
         for path in &candidates {
@@ -115,22 +154,9 @@ impl Config {
         }
 
         // Not found: create default config in user config dir
-        #[cfg(windows)]
-        let default_path = {
-            if let Some(appdata) = std::env::var_os("APPDATA") {
-                PathBuf::from(appdata).join("ps3-redump-downloader/config.ini")
-            } else {
-                PathBuf::from("config.ini")
-            }
-        };
-        #[cfg(not(windows))]
-        let default_path = {
-            if let Some(home) = std::env::var_os("HOME") {
-                PathBuf::from(home).join(".config/ps3-redump-downloader/config.ini")
-            } else {
-                PathBuf::from("config.ini")
-            }
-        };
+        let default_path = Self::user_config_dir()
+            .map(|dir| dir.join("config.ini"))
+            .unwrap_or_else(|| PathBuf::from("config.ini"));
 
         // Ensure parent directory exists
         if let Some(parent) = default_path.parent() {
@@ -141,6 +167,15 @@ impl Config {
         let default_config = r#"[url]
 PS3_ISO = https://myrient.erista.me/files/Redump/Sony%20-%20PlayStation%203/
 PS3_KEYS = https://myrient.erista.me/files/Redump/Sony%20-%20PlayStation%203%20-%20Disc%20Keys%20TXT/
+# Further mirrors, tried in order when the one above fails (comma separated)
+PS3_ISO_MIRRORS =
+PS3_KEYS_MIRRORS =
+# Other Redump platforms, listed next to the PS3 games when set (mirrors may follow, comma
+# separated). Their games need no key and are kept as extracted, e.g.
+# PS2_ISO = https://myrient.erista.me/files/Redump/Sony%20-%20PlayStation%202/
+PS1_ISO =
+PS2_ISO =
+PSP_ISO =
 
 [Download]
 LIST_PS3_FILES_JSON_NAME = listPS3Titles.json
@@ -148,6 +183,7 @@ EXTERNAL_ISO = 0
 MAX_RETRIES = 10
 DELAY_BETWEEN_RETRIES = 10
 TIMEOUT_REQUEST = 1800
+CACHE_TTL_HOURS = 24
 
 [folder]
 TMP_FOLDER_NAME = ~/PS3-Games
@@ -156,6 +192,11 @@ TMP_ISO_FOLDER_NAME = iso_files
 [PS3]
 DECRYPTOR_PATH = /path/to/PS3Dec
 DECRYPTION_TIMEOUT = 300
+
+[1g1r]
+REGIONS = Europe, USA, World, Japan
+LANGUAGES = En
+REQUIRE_LANGUAGE = 0
 "#;
         let mut file = fs::File::create(&default_path)
             .map_err(|e| anyhow::anyhow!("Failed to create default config at {}: {}", default_path.display(), e))?;
@@ -168,19 +209,86 @@ DECRYPTION_TIMEOUT = 300
         Ok((cfg, default_path))
     }
 
+    /// Returns the per-user config folder (`~/.config/ps3-redump-downloader` or `%APPDATA%\\ps3-redump-downloader`).
+    pub fn user_config_dir() -> Option<std::path::PathBuf> {
+        #[cfg(windows)]
+        let base = std::env::var_os("APPDATA").map(std::path::PathBuf::from);
+        #[cfg(not(windows))]
+        let base = std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".config"));
+        base.map(|base| base.join("ps3-redump-downloader"))
+    }
+
+    /// Returns the path to the interactive search history, inside the user config folder.
+    pub fn history_path() -> Option<std::path::PathBuf> {
+        Self::user_config_dir().map(|dir| dir.join("search_history.txt"))
+    }
+
+    /// Splits a priority list written as "Europe, USA" or "Europe > USA".
+    pub fn parse_priority(value: &str) -> Vec<String> {
+        value
+            .split([',', '>'])
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Splits a list of URLs separated by commas or whitespace.
+    pub fn parse_urls(value: &str) -> Vec<String> {
+        value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Returns the PS3 ISO listing URL followed by its mirrors.
+    pub fn iso_urls(&self) -> Vec<String> {
+        std::iter::once(self.ps3_iso_url.clone())
+            .chain(self.ps3_iso_mirrors.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the listing URL of a platform followed by its mirrors, or nothing if the
+    /// platform is not configured.
+    pub fn iso_urls_for(&self, game_type: GameType) -> Vec<String> {
+        if game_type == GameType::PS3 {
+            return self.iso_urls();
+        }
+        self.platform_iso_urls
+            .iter()
+            .find(|(platform, _)| *platform == game_type)
+            .map(|(_, urls)| urls.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the platforms whose games are listed: PS3, then every configured one.
+    pub fn platforms(&self) -> Vec<GameType> {
+        std::iter::once(GameType::PS3)
+            .chain(self.platform_iso_urls.iter().map(|(platform, _)| *platform))
+            .collect()
+    }
+
+    /// Returns the PS3 keys listing URL followed by its mirrors.
+    pub fn keys_urls(&self) -> Vec<String> {
+        std::iter::once(self.ps3_keys_url.clone())
+            .chain(self.ps3_keys_mirrors.iter().cloned())
+            .collect()
+    }
+
     /// Expands a path that starts with ~ to the user's home directory.
-    fn expand_tilde(path: &str) -> std::path::PathBuf {
-        if path.starts_with("~/") {
+    pub fn expand_tilde(path: &str) -> std::path::PathBuf {
+        if let Some(rest) = path.strip_prefix("~/") {
             #[cfg(windows)]
             {
                 if let Some(home) = std::env::var_os("USERPROFILE") {
-                    return std::path::PathBuf::from(home).join(&path[2..]);
+                    return std::path::PathBuf::from(home).join(rest);
                 }
             }
             #[cfg(not(windows))]
             {
                 if let Some(home) = std::env::var_os("HOME") {
-                    return std::path::PathBuf::from(home).join(&path[2..]);
+                    return std::path::PathBuf::from(home).join(rest);
                 }
             }
         }
@@ -202,6 +310,17 @@ DECRYPTION_TIMEOUT = 300
         Self::expand_tilde(&self.tmp_folder_name).join(&self.list_ps3_files_json_name)
     }
 
+    /// Returns how long the game list and keys list caches are used before checking for changes.
+    /// Zero means they never expire.
+    pub fn cache_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cache_ttl_hours * 3600)
+    }
+
+    /// Returns the expanded path to the catalogue database, next to the JSON game list it replaces.
+    pub fn catalog_db_path(&self) -> std::path::PathBuf {
+        Self::expand_tilde(&self.tmp_folder_name).join("catalog.db")
+    }
+
     /// Returns the expanded path to the decryption binary.
     pub fn decryptor_path(&self) -> std::path::PathBuf {
         Self::expand_tilde(&self.decryptor_path)
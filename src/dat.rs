@@ -0,0 +1,128 @@
+use crate::{error::Error, models::DumpInfo};
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fs;
+use std::path::Path;
+
+/// One game of a Redump DAT file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatEntry {
+    /// Game name, the catalogue title without .zip
+    pub name: String,
+    /// Serial(s), e.g. "BLUS-30001" or "BCES-00569, BCES-00569/P"
+    pub serial: Option<String>,
+    /// Size and hashes of the disc image
+    pub dump: Option<DumpInfo>,
+}
+
+/// Reads a Redump DAT file (Logiqx XML, as published per system on redump.org).
+pub fn read_dat(path: &Path) -> Result<Vec<DatEntry>> {
+    let content = fs::read_to_string(path).map_err(|e| Error::filesystem("read", path, e))?;
+    parse_dat(&content).with_context(|| format!("Invalid DAT file {}", path.display()))
+}
+
+/// Parses the `<game>` entries of a Logiqx XML DAT. The serial is read from a `<serial>`
+/// element or a `serial` attribute; the dump is the `.iso` track, or the first track if
+/// none is named so.
+pub fn parse_dat(content: &str) -> Result<Vec<DatEntry>> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut current: Option<DatEntry> = None;
+    let mut in_serial = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if is_game(&element) => {
+                current = Some(DatEntry {
+                    name: attribute(&element, b"name")?.unwrap_or_default(),
+                    serial: attribute(&element, b"serial")?,
+                    dump: None,
+                });
+            }
+            Event::Start(element) if element.name().as_ref() == b"serial" => in_serial = true,
+            Event::End(element) if element.name().as_ref() == b"serial" => in_serial = false,
+            Event::Text(text) if in_serial => {
+                if let Some(entry) = current.as_mut() {
+                    let serial = text.unescape()?.trim().to_string();
+                    entry.serial = (!serial.is_empty()).then_some(serial);
+                }
+            }
+            Event::Start(element) | Event::Empty(element) if element.name().as_ref() == b"rom" => {
+                if let Some(entry) = current.as_mut() {
+                    read_rom(entry, &element)?;
+                }
+            }
+            Event::End(element) if matches!(element.name().as_ref(), b"game" | b"machine") => {
+                if let Some(entry) = current.take().filter(|entry| !entry.name.is_empty()) {
+                    entries.push(entry);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns true for a game element (`<machine>` in newer Logiqx DATs).
+fn is_game(element: &BytesStart) -> bool {
+    matches!(element.name().as_ref(), b"game" | b"machine")
+}
+
+/// Takes the size and hashes of a `<rom>` track, unless an `.iso` track was already read.
+fn read_rom(entry: &mut DatEntry, element: &BytesStart) -> Result<()> {
+    let name = attribute(element, b"name")?.unwrap_or_default();
+    let is_iso = name.to_lowercase().ends_with(".iso");
+    if entry.dump.is_some() && !is_iso {
+        return Ok(());
+    }
+    let Some(size) = attribute(element, b"size")?.and_then(|size| size.parse().ok()) else {
+        return Ok(());
+    };
+    let hash = |name: &[u8]| -> Result<Option<String>> { Ok(attribute(element, name)?.map(|hash| hash.to_lowercase())) };
+    entry.dump = Some(DumpInfo {
+        size,
+        crc32: hash(b"crc")?,
+        md5: hash(b"md5")?,
+        sha1: hash(b"sha1")?,
+    });
+    if entry.serial.is_none() {
+        entry.serial = attribute(element, b"serial")?;
+    }
+    Ok(())
+}
+
+/// Returns an attribute value, unescaped.
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_ref() == name {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Normalises a serial or title ID for comparison: "BLUS-30001", "blus 30001" and
+/// "BLUS30001" are the same.
+pub fn normalize_serial(serial: &str) -> String {
+    serial
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Returns true if the text looks like a PS3 serial or title ID: four letters and five
+/// digits, e.g. "BLUS-30001" or "NPUB30001".
+pub fn is_serial(text: &str) -> bool {
+    let normalized = normalize_serial(text);
+    let text = text.trim();
+    let allowed = text.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ' ' || c == '_');
+    allowed
+        && normalized.len() == 9
+        && normalized[..4].chars().all(|c| c.is_ascii_alphabetic())
+        && normalized[4..].chars().all(|c| c.is_ascii_digit())
+}
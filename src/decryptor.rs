@@ -1,14 +1,19 @@
-use crate::{config::Config, key_manager::KeyManager};
+use crate::{
+    config::Config,
+    error::Error,
+    key_manager::KeyManager,
+    progress::{Phase, Reporter},
+};
 use anyhow::Result;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::path::Path;
+use std::process::Stdio;
 use tokio::process::Command;
-use std::io::Write;
 
 /// Decryptor handles PS3 ISO decryption using the PS3Dec C binary and keys.
 pub struct Decryptor {
     config: Config,
     key_manager: KeyManager,
+    reporter: Reporter,
 }
 
 impl Decryptor {
@@ -17,47 +22,47 @@ impl Decryptor {
         Self {
             config: config.clone(),
             key_manager: KeyManager::new(config),
+            reporter: Reporter::console(),
         }
     }
 
+    /// Routes status lines and progress bars through the given reporter.
+    pub fn with_reporter(mut self, reporter: Reporter) -> Self {
+        self.key_manager = self.key_manager.with_reporter(reporter.clone());
+        self.reporter = reporter;
+        self
+    }
+
     /// Decrypts a PS3 ISO file using the PS3Dec C binary and key.
     pub async fn decrypt_iso(&self, encrypted_path: &Path, decrypted_path: &Path, key: &str) -> Result<()> {
         use std::fs;
         use std::time::Duration;
         use tokio::time::sleep;
-        use indicatif::ProgressDrawTarget;
 
         let decryptor_path = self.config.decryptor_path();
         
         // Check if decryption binary exists
         if !decryptor_path.exists() {
-            anyhow::bail!(
-                "PS3Dec binary not found at: {}. Please build the PS3Dec program first:\n\
-                 cd decryptor/PS3Dec && mkdir -p build && cd build && cmake .. && make",
-                decryptor_path.display()
-            );
+            return Err(Error::DecryptorMissing { path: decryptor_path }.into());
         }
 
         let input_size = fs::metadata(encrypted_path)
             .map(|m| m.len())
             .unwrap_or(0);
         if input_size == 0 {
-            anyhow::bail!("Encrypted ISO file is empty or missing: {}", encrypted_path.display());
+            return Err(Error::Decryption {
+                reason: format!("Encrypted ISO file is empty or missing: {}", encrypted_path.display()),
+            }
+            .into());
         }
 
-        println!("Decrypting PS3 ISO file with key...");
-        std::io::stdout().flush().ok();
+        self.reporter.message("Decrypting PS3 ISO file with key...");
         // Create progress bar for decryption
-        let progress_bar = ProgressBar::new(input_size);
-        progress_bar.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                .unwrap()
-                .progress_chars("#>-")
+        let progress_bar = self.reporter.bar(
+            Phase::Decrypt,
+            Some(input_size),
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
         );
-        progress_bar.set_draw_target(ProgressDrawTarget::stdout());
-        progress_bar.tick();
-        std::io::stdout().flush().ok();
 
         // Build command for PS3Dec: PS3Dec d key <key> <input> <output>
         let mut command = Command::new(&decryptor_path);
@@ -67,8 +72,15 @@ impl Decryptor {
         command.arg(encrypted_path); // input file
         command.arg(decrypted_path); // output file
 
+        // PS3Dec's own output would draw over a captured (e.g. full-screen) display
+        if self.reporter.is_captured() {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+
         // Start the decryption process
-        let mut child = command.spawn()?;
+        let mut child = command.spawn().map_err(|e| Error::Decryption {
+            reason: format!("could not start {}: {}", decryptor_path.display(), e),
+        })?;
         let timeout_duration = Duration::from_secs(self.config.decryption_timeout);
         let poll_interval = Duration::from_millis(500);
         let mut last_size = 0;
@@ -76,7 +88,7 @@ impl Decryptor {
         let max_stalled = 20; // 10 seconds
         let start_time = std::time::Instant::now();
 
-        let mut used_spinner = false;
+        let mut spinner = None;
         // Progress bar loop
         loop {
             // Check if process has exited
@@ -89,13 +101,14 @@ impl Decryptor {
                     }
                     if status.success() {
                         progress_bar.finish_with_message("Decryption completed");
-                        std::io::stdout().flush().ok();
                         break;
                     } else {
                         progress_bar.abandon_with_message("Decryption failed");
-                        std::io::stdout().flush().ok();
                         let stderr = status.code().map(|c| format!("Exit code: {}", c)).unwrap_or_else(|| "Unknown error".to_string());
-                        anyhow::bail!("PS3Dec failed: {}", stderr);
+                        return Err(Error::Decryption {
+                            reason: format!("PS3Dec failed: {}", stderr),
+                        }
+                        .into());
                     }
                 }
                 None => {
@@ -109,28 +122,18 @@ impl Decryptor {
                             stalled_count = 0;
                         }
                         last_size = size;
-                        if stalled_count > max_stalled {
-                            if !used_spinner {
-                                progress_bar.println("Warning: Decryption appears stalled. Output file size is not growing. Showing spinner instead.");
-                                progress_bar.abandon_with_message("Decryption appears stalled");
-                                let spinner = ProgressBar::new_spinner();
-                                spinner.set_style(
-                                    ProgressStyle::default_spinner()
-                                        .template("{spinner:.green} Decrypting... {elapsed_precise}")
-                                        .unwrap()
-                                        .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
-                                );
-                                spinner.set_draw_target(ProgressDrawTarget::stdout());
-                                spinner.enable_steady_tick(Duration::from_millis(120));
-                                used_spinner = true;
-                            }
+                        if stalled_count > max_stalled && spinner.is_none() {
+                            progress_bar.stalled("Warning: Decryption appears stalled. Output file size is not growing. Showing spinner instead.");
+                            spinner = Some(self.reporter.spinner("{spinner:.green} Decrypting... {elapsed_precise}"));
                         }
                     }
                     if start_time.elapsed() > timeout_duration {
                         progress_bar.abandon_with_message("Decryption timed out");
-                        std::io::stdout().flush().ok();
                         let _ = child.kill().await;
-                        anyhow::bail!("Decryption timed out after {} seconds", self.config.decryption_timeout);
+                        return Err(Error::DecryptionTimeout {
+                            seconds: self.config.decryption_timeout,
+                        }
+                        .into());
                     }
                     sleep(poll_interval).await;
                 }
@@ -144,10 +147,15 @@ impl Decryptor {
                 progress_bar.println("Warning: Decrypted file is much smaller than the input. Decryption may have failed.");
             }
         } else {
-            anyhow::bail!("Decryption failed: Output file was not created.");
+            return Err(Error::Decryption {
+                reason: "Output file was not created.".to_string(),
+            }
+            .into());
+        }
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
         }
-        println!("PS3 ISO decryption completed successfully");
-        std::io::stdout().flush().ok();
+        self.reporter.message("PS3 ISO decryption completed successfully");
         Ok(())
     }
 
@@ -156,11 +164,7 @@ impl Decryptor {
         let decryptor_path = self.config.decryptor_path();
         
         if !decryptor_path.exists() {
-            anyhow::bail!(
-                "PS3Dec binary not found at: {}. Please build the PS3Dec program first:\n\
-                 cd decryptor/PS3Dec && mkdir -p build && cd build && cmake .. && make",
-                decryptor_path.display()
-            );
+            return Err(Error::DecryptorMissing { path: decryptor_path }.into());
         }
 
         // Check if file is executable (Unix-like systems)
@@ -169,11 +173,7 @@ impl Decryptor {
             use std::os::unix::fs::PermissionsExt;
             if let Ok(metadata) = std::fs::metadata(&decryptor_path) {
                 if metadata.permissions().mode() & 0o111 == 0 {
-                    anyhow::bail!(
-                        "PS3Dec binary is not executable: {}. Please make it executable with 'chmod +x {}'",
-                        decryptor_path.display(),
-                        decryptor_path.display()
-                    );
+                    return Err(Error::DecryptorNotExecutable { path: decryptor_path }.into());
                 }
             }
         }
@@ -0,0 +1,167 @@
+use crate::{
+    config::Config,
+    key_manager::KeyManager,
+    models::{DumpInfo, Game},
+    utils::format_size,
+};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What is known about a game before downloading it, gathered from local state only.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameDetails {
+    /// Full Redump title
+    pub title: String,
+    pub region: Option<String>,
+    /// Size as listed in the catalogue
+    pub size: String,
+    /// Size in bytes, when the listed size could be parsed
+    pub size_bytes: Option<u64>,
+    /// Serial(s) from the imported Redump DAT
+    pub serial: Option<String>,
+    /// Exact ISO size and hashes from the imported Redump DAT
+    pub dump: Option<DumpInfo>,
+    /// SHA-1 of the ZIP, if the listing gives it
+    pub zip_sha1: Option<String>,
+    /// Redump platform, e.g. "PS2"
+    pub platform: &'static str,
+    /// Whether the game needs a key and decryption (only PS3 games do)
+    pub needs_decryption: bool,
+    /// Whether the keys cache lists a key for the game (`None` if the keys list was never
+    /// fetched or the game needs no key)
+    pub key_cached: Option<bool>,
+    /// Folder the game is downloaded and decrypted into
+    pub output_folder: PathBuf,
+    /// Decrypted ISO filename before the PARAM.SFO rename; `None` when the extracted files
+    /// are kept as they are
+    pub output_filename: Option<String>,
+    /// A decrypted ISO (or extracted CUE sheet or ISO) already present in the output folder
+    pub existing_iso: Option<PathBuf>,
+    /// Free space on the disk holding the output folder, in bytes
+    pub free_space: Option<u64>,
+}
+
+impl GameDetails {
+    /// Collects the details of a game without any network access.
+    pub fn collect(config: &Config, game: &Game) -> Self {
+        let output_folder = game.output_folder(&config.tmp_iso_folder_path());
+
+        Self {
+            title: game.clean_title(),
+            region: game.region.clone(),
+            size: game.size.clone(),
+            size_bytes: game.size_bytes,
+            serial: game.serial.clone(),
+            dump: game.dump.clone(),
+            zip_sha1: game.zip_sha1.clone(),
+            platform: game.game_type.name(),
+            needs_decryption: game.needs_decryption,
+            key_cached: if game.needs_decryption {
+                KeyManager::new(config).has_cached_key(game)
+            } else {
+                None
+            },
+            existing_iso: find_downloaded_iso(config, game),
+            free_space: free_space(&output_folder),
+            output_filename: game.needs_decryption.then(|| game.output_iso_filename()),
+            output_folder,
+        }
+    }
+
+    /// Rough peak disk usage while processing: the encrypted and decrypted ISOs coexist
+    /// during decryption, and each is about as large as the download.
+    pub fn space_needed(&self) -> Option<u64> {
+        self.size_bytes.map(|size| size.saturating_mul(2))
+    }
+}
+
+/// Prints the details of a game before asking to confirm the download.
+pub fn print_game_details(details: &GameDetails) {
+    println!("\n{}", details.title);
+    println!("  Platform:     {}", details.platform);
+    println!("  Region:       {}", details.region.as_deref().unwrap_or("Unknown"));
+    println!("  Size:         {}", details.size);
+    if let Some(serial) = &details.serial {
+        println!("  Serial:       {}", serial);
+    }
+    if let Some(dump) = &details.dump {
+        println!("  ISO size:     {} bytes ({})", dump.size, format_size(dump.size));
+        if let Some(sha1) = &dump.sha1 {
+            println!("  SHA-1:        {}", sha1);
+        }
+    }
+    if let Some(zip_sha1) = &details.zip_sha1 {
+        println!("  ZIP SHA-1:    {}", zip_sha1);
+    }
+    println!(
+        "  Key:          {}",
+        match details.key_cached {
+            _ if !details.needs_decryption => "not needed",
+            Some(true) => "available in keys cache",
+            Some(false) => "NOT in keys cache, decryption will likely fail",
+            None => "unknown (keys list not fetched yet)",
+        }
+    );
+    match &details.existing_iso {
+        Some(path) => println!(
+            "  Downloaded:   yes, {} (downloading again replaces it)",
+            path.display()
+        ),
+        None => println!("  Downloaded:   no"),
+    }
+    match &details.output_filename {
+        Some(filename) => {
+            println!("  Output:       {}", details.output_folder.join(filename).display());
+            println!("                (renamed to TITLE_ID-TITLE.iso when PARAM.SFO can be read)");
+        }
+        None => println!(
+            "  Output:       {} (files kept as extracted from the ZIP)",
+            details.output_folder.display()
+        ),
+    }
+    println!("  Free space:   {}", describe_free_space(details.free_space, details.space_needed()));
+}
+
+/// Describes the free space, warning when it is below what the download needs.
+pub fn describe_free_space(free: Option<u64>, needed: Option<u64>) -> String {
+    match (free, needed) {
+        (Some(free), Some(needed)) if free < needed => format!(
+            "{} (NOT ENOUGH, about {} needed while decrypting)",
+            format_size(free),
+            format_size(needed)
+        ),
+        (Some(free), Some(needed)) => format!("{} (about {} needed)", format_size(free), format_size(needed)),
+        (Some(free), None) => format_size(free),
+        (None, _) => "unknown".to_string(),
+    }
+}
+
+/// Returns the decrypted ISO of a game if it is already in its output folder,
+/// ignoring a leftover encrypted one. For platforms without encryption it is the
+/// extracted CUE sheet, or else the ISO.
+pub fn find_downloaded_iso(config: &Config, game: &Game) -> Option<PathBuf> {
+    let folder = game.output_folder(&config.tmp_iso_folder_path());
+    let paths: Vec<PathBuf> = fs::read_dir(folder).ok()?.flatten().map(|entry| entry.path()).collect();
+    let has_extension = |path: &PathBuf, extension: &str| {
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+    };
+    if !game.needs_decryption {
+        return paths
+            .iter()
+            .find(|path| has_extension(path, "cue"))
+            .or_else(|| paths.iter().find(|path| has_extension(path, "iso")))
+            .cloned();
+    }
+    let encrypted_name = format!("{}.iso", game.clean_title());
+    paths.into_iter().find(|path| {
+        has_extension(path, "iso") && path.file_name().is_some_and(|name| name != encrypted_name.as_str())
+    })
+}
+
+/// Returns the space available on the disk holding `path`, using the nearest existing parent.
+pub fn free_space(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find(|dir| dir.exists())
+        .and_then(|dir| fs4::available_space(dir).ok())
+}
@@ -0,0 +1,195 @@
+use crate::{
+    catalog::{ChangeKind, RefreshDiff},
+    error::classify,
+    models::{Game, GameType},
+    search::SearchMatch,
+    utils::{format_size, format_timestamp},
+};
+use anyhow::Result;
+use serde::Serialize;
+
+/// Displays the list of filtered PS3 games with their titles and sizes.
+pub fn print_games(games: &[&Game]) {
+    let indexed: Vec<(usize, &Game)> = games
+        .iter()
+        .enumerate()
+        .map(|(index, game)| (index + 1, *game))
+        .collect();
+    print_indexed_games(&indexed);
+}
+
+/// Displays games next to an explicit index (e.g. their position in the full catalogue),
+/// as the name followed by the parsed regions, languages and tags.
+pub fn print_indexed_games(games: &[(usize, &Game)]) {
+    for (index, game) in games {
+        println!("{}. {}", index, describe_game(game));
+    }
+    print_games_footer(games);
+}
+
+/// Formats a game as its name followed by the parsed regions, languages, tags and size.
+/// Games of platforms other than PS3 are prefixed with theirs.
+fn describe_game(game: &Game) -> String {
+    let mut line = match game.game_type {
+        GameType::PS3 => game.name.clone(),
+        game_type => format!("[{}] {}", game_type.name(), game.name),
+    };
+    if !game.regions.is_empty() {
+        line.push_str(&format!(" [{}]", game.regions.join(", ")));
+    }
+    if !game.languages.is_empty() {
+        line.push_str(&format!(" [{}]", game.languages.join(",")));
+    }
+    for tag in &game.tags {
+        line.push_str(&format!(" [{}]", tag));
+    }
+    match &game.serial {
+        Some(serial) => format!("{} ({}) {}", line, game.size, serial),
+        None => format!("{} ({})", line, game.size),
+    }
+}
+
+/// Prints how many games were listed and their combined size.
+fn print_games_footer(games: &[(usize, &Game)]) {
+    let games: Vec<&Game> = games.iter().map(|(_, game)| *game).collect();
+    let noun = if games.len() == 1 { "title" } else { "titles" };
+    println!("{} {}, {}\n", games.len(), noun, describe_total_size(&games));
+}
+
+/// Prints what the given refreshes changed, newest first, then the recently dated dumps
+/// with their listing date and catalogue index.
+pub fn print_whats_new(diffs: &[RefreshDiff], recent: &[(usize, &Game)], days: u64) {
+    if diffs.is_empty() {
+        println!("No catalogue refresh in the last {} days changed anything\n", days);
+    }
+    for diff in diffs {
+        println!(
+            "Refresh of {} UTC: {} added, {} removed, {} changed in size",
+            format_timestamp(diff.refreshed_at),
+            diff.count(ChangeKind::Added),
+            diff.count(ChangeKind::Removed),
+            diff.count(ChangeKind::Resized)
+        );
+        for change in &diff.changes {
+            let title = match change.game_type {
+                GameType::PS3 => change.title.trim_end_matches(".zip").to_string(),
+                game_type => format!("[{}] {}", game_type.name(), change.title.trim_end_matches(".zip")),
+            };
+            let old_size = change.old_size.as_deref().unwrap_or_default();
+            let new_size = change.new_size.as_deref().unwrap_or_default();
+            match change.kind {
+                ChangeKind::Added => println!("  + {} ({})", title, new_size),
+                ChangeKind::Removed => println!("  - {} ({})", title, old_size),
+                ChangeKind::Resized => println!("  ~ {} ({} -> {})", title, old_size, new_size),
+            }
+        }
+        println!();
+    }
+
+    if recent.is_empty() {
+        println!("No dumps dated in the last {} days\n", days);
+        return;
+    }
+    println!("Dumps dated in the last {} days, newest first:", days);
+    for (index, game) in recent {
+        println!("{}  {}. {}", game.date.as_deref().unwrap_or_default(), index, describe_game(game));
+    }
+    print_games_footer(recent);
+}
+
+/// Prints the refreshes and recently dated dumps of `print_whats_new` as a JSON object.
+pub fn print_whats_new_json(diffs: &[RefreshDiff], recent: &[(usize, &Game)]) -> Result<()> {
+    let recent: Vec<IndexedGame> = recent
+        .iter()
+        .map(|(index, game)| IndexedGame {
+            index: *index,
+            score: None,
+            game,
+        })
+        .collect();
+    print_json(&serde_json::json!({ "refreshes": diffs, "recent": recent }))
+}
+
+/// Formats the combined size of the games, noting how many have an unknown size.
+pub fn describe_total_size(games: &[&Game]) -> String {
+    let total: u64 = games.iter().filter_map(|game| game.size_bytes).sum();
+    let unknown = games.iter().filter(|game| game.size_bytes.is_none()).count();
+    if unknown > 0 {
+        format!("{} (+{} of unknown size)", format_size(total), unknown)
+    } else {
+        format_size(total)
+    }
+}
+
+/// A catalogue entry together with its 1-based catalogue index, as emitted in JSON mode.
+#[derive(Serialize)]
+struct IndexedGame<'a> {
+    index: usize,
+    /// Search relevance, only present for search results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(flatten)]
+    game: &'a Game,
+}
+
+/// Prints games with their index as a JSON array.
+pub fn print_indexed_games_json(games: &[(usize, &Game)]) -> Result<()> {
+    let games: Vec<IndexedGame> = games
+        .iter()
+        .map(|(index, game)| IndexedGame {
+            index: *index,
+            score: None,
+            game,
+        })
+        .collect();
+    print_json(&games)
+}
+
+/// Prints search results with their index and relevance score as a JSON array, best first.
+pub fn print_search_matches_json(matches: &[SearchMatch]) -> Result<()> {
+    let games: Vec<IndexedGame> = matches
+        .iter()
+        .map(|hit| IndexedGame {
+            index: hit.index,
+            score: Some(hit.score),
+            game: hit.game,
+        })
+        .collect();
+    print_json(&games)
+}
+
+/// Prints any serializable value as pretty JSON on stdout.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints an error as a JSON object on stdout, with its chain of causes.
+pub fn print_json_error(error: &anyhow::Error) {
+    if error.is::<ReportedError>() {
+        return;
+    }
+    let (kind, exit_code) = classify(error);
+    let value = serde_json::json!({
+        "ok": false,
+        "error": {
+            "kind": kind,
+            "exit_code": exit_code,
+            "message": error.to_string(),
+            "causes": error.chain().skip(1).map(|e| e.to_string()).collect::<Vec<_>>(),
+        }
+    });
+    println!("{}", serde_json::to_string_pretty(&value).unwrap_or_default());
+}
+
+/// An error whose details were already printed as JSON, so `print_json_error` is skipped.
+#[derive(Debug)]
+pub struct ReportedError(pub String);
+
+impl std::fmt::Display for ReportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReportedError {}
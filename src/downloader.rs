@@ -1,11 +1,23 @@
-use crate::{config::Config, decryptor::Decryptor, models::Game};
+use crate::{
+    catalog::Catalog,
+    config::Config,
+    decryptor::Decryptor,
+    details::{find_downloaded_iso, free_space},
+    error::Error,
+    key_manager::KeyManager,
+    models::Game,
+    progress::{Phase, ProgressEvent, Reporter},
+    source::Mirrors,
+};
 use anyhow::Result;
 use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tokio::fs::OpenOptions;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt, SeekFrom};
 use zip::ZipArchive;
@@ -70,10 +82,105 @@ mod sfo {
     }
 }
 
-/// Downloader handles downloading, extracting, and decrypting PS3 ISO files.
+/// Summary of one processed game: where things went, which key was used and how long each step took.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadReport {
+    /// The cleaned title of the game
+    pub title: String,
+    /// The URL the ZIP was downloaded from
+    pub url: String,
+    /// The decryption key used
+    pub key: String,
+    /// The per-title folder the files were written to
+    pub folder: PathBuf,
+    /// The final decrypted ISO, after renaming from PARAM.SFO; for platforms without
+    /// encryption, the extracted CUE sheet or ISO
+    pub iso_path: Option<PathBuf>,
+    /// The final ISO file name
+    pub iso_name: Option<String>,
+    /// Size of the downloaded ZIP in bytes
+    pub zip_bytes: Option<u64>,
+    /// Size of the extracted, still encrypted ISO in bytes
+    pub encrypted_bytes: Option<u64>,
+    /// Size of the decrypted ISO (or extracted CUE sheet or ISO) in bytes
+    pub iso_bytes: Option<u64>,
+    /// Seconds spent downloading
+    pub download_secs: f64,
+    /// Seconds spent extracting
+    pub extract_secs: f64,
+    /// Seconds spent decrypting and renaming
+    pub decrypt_secs: f64,
+    /// Seconds for the whole game, including the key lookup
+    pub total_secs: f64,
+    /// What would have been done, when this was a dry run
+    pub plan: Option<DownloadPlan>,
+}
+
+/// What a download would do, worked out by a dry run without fetching the ISO.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadPlan {
+    /// Size of the ZIP as reported by the server
+    pub remote_size: Option<u64>,
+    /// The decryptor binary that would be run
+    pub decryptor: PathBuf,
+    /// Whether the ZIP would be downloaded manually through the browser
+    pub external_download: bool,
+    /// Filesystem changes, in the order they would happen
+    pub actions: Vec<PlannedAction>,
+    /// Everything that would make the real download fail
+    pub problems: Vec<String>,
+}
+
+impl DownloadPlan {
+    /// Returns true if nothing was found that would make the download fail.
+    pub fn is_ready(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A filesystem change a download would make.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction {
+    pub action: PathAction,
+    pub path: PathBuf,
+    /// Why the change happens
+    pub detail: String,
+}
+
+/// Kind of filesystem change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathAction {
+    CreateDir,
+    /// Recursive removal of a folder and everything in it
+    DeleteDir,
+    WriteFile,
+    DeleteFile,
+    /// Renamed to the path given in the detail
+    Rename,
+}
+
+impl PathAction {
+    /// Returns a short human readable name of the change.
+    pub fn label(&self) -> &'static str {
+        match self {
+            PathAction::CreateDir => "create dir",
+            PathAction::DeleteDir => "DELETE DIR",
+            PathAction::WriteFile => "write",
+            PathAction::DeleteFile => "delete",
+            PathAction::Rename => "rename",
+        }
+    }
+}
+
+/// Downloader handles downloading, extracting, and decrypting PS3 ISO files, and downloading
+/// and extracting the images of the other platforms.
 pub struct Downloader {
     config: Config,
     decryptor: Decryptor,
+    reporter: Reporter,
+    /// Only plan downloads instead of running them
+    dry_run: bool,
 }
 
 impl Downloader {
@@ -82,33 +189,95 @@ impl Downloader {
         Self {
             config: config.clone(),
             decryptor: Decryptor::new(config),
+            reporter: Reporter::console(),
+            dry_run: false,
         }
     }
 
-    /// Download, extract, and decrypt the selected PS3 game.
-    pub async fn download_ps3_element(&self, game: &Game) -> Result<()> {
+    /// Routes status lines and progress bars through the given reporter.
+    pub fn with_reporter(mut self, reporter: Reporter) -> Self {
+        self.decryptor = self.decryptor.with_reporter(reporter.clone());
+        self.reporter = reporter;
+        self
+    }
+
+    /// Makes every download a dry run: the key, the remote size and the decryptor are
+    /// checked and the plan is printed, but nothing is downloaded or written.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Returns true if downloads are only planned.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns the configuration used for downloads.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the reporter status lines and progress go to.
+    pub fn reporter(&self) -> &Reporter {
+        &self.reporter
+    }
+
+    /// Download, extract, and decrypt the selected game; games of platforms without
+    /// encryption are only downloaded and extracted.
+    pub async fn download_ps3_element(&self, game: &Game) -> Result<DownloadReport> {
+        let result = self.process_game(game).await;
+        match &result {
+            Err(e) => self.reporter.send(ProgressEvent::Error {
+                title: game.clean_title(),
+                message: e.to_string(),
+            }),
+            // Remembered in the catalogue database next to the game
+            Ok(DownloadReport { iso_path: Some(iso_path), plan: None, .. }) => {
+                if let Err(e) = Catalog::open(&self.config).and_then(|catalog| catalog.record_download(game, iso_path)) {
+                    tracing::warn!("Failed to record the download in the catalogue database: {:#}", e);
+                }
+            }
+            Ok(_) => {}
+        }
+        result
+    }
+
+    async fn process_game(&self, game: &Game) -> Result<DownloadReport> {
+        let started = Instant::now();
         let title = game.clean_title();
-        println!("\nSelected {}\n", title);
+        self.reporter.message(format!("\nSelected {}\n", title));
+
+        if self.dry_run {
+            let mut report = self.plan_game(game).await;
+            report.total_secs = started.elapsed().as_secs_f64();
+            return Ok(report);
+        }
+
+        if !game.needs_decryption {
+            let mut report = self.download_extract_and_decrypt(game, None).await?;
+            self.reporter.message(format!("\n{} downloaded :)", title));
+            self.open_explorer(&report.folder);
+            report.total_secs = started.elapsed().as_secs_f64();
+            return Ok(report);
+        }
 
         // Validate decryption binary before starting
         self.decryptor.validate_decryptor()?;
 
         // Download the key for this game
-        println!("Downloading decryption key...");
+        self.reporter.message("Downloading decryption key...");
         let key = self.decryptor.key_manager().find_key_for_game(game).await?;
 
         if key.is_none() {
-            anyhow::bail!("Could not find decryption key for game: {}. The game may not be available or the key may not exist.", title);
+            return Err(Error::KeyNotFound { title }.into());
         }
 
         let key = key.unwrap();
-        println!("Found decryption key for {}", title);
+        self.reporter.message(format!("Found decryption key for {}", title));
 
-        // Construct the full URL by combining base URL with relative path
-        let full_url = format!("{}{}", self.config.ps3_iso_url, game.link);
-        self.download_extract_and_decrypt(&full_url, game, &key)
-            .await?;
-        println!("\n{} downloaded and decrypted :)", title);
+        let mut report = self.download_extract_and_decrypt(game, Some(&key)).await?;
+        self.reporter.message(format!("\n{} downloaded and decrypted :)", title));
 
         // Open the folder containing the decrypted ISO
         let decrypted_iso_file = self
@@ -119,21 +288,221 @@ impl Downloader {
             self.open_explorer(&decrypted_iso_file);
         }
 
-        Ok(())
+        report.total_secs = started.elapsed().as_secs_f64();
+        Ok(report)
     }
 
-    /// Download, extract, and decrypt the file, handling both direct and external download methods.
-    async fn download_extract_and_decrypt(&self, link: &str, game: &Game, key: &str) -> Result<()> {
-        println!(" # PS3 ISO file...");
+    /// Works out what `process_game` would do for the game without downloading or writing anything,
+    /// and prints the plan.
+    async fn plan_game(&self, game: &Game) -> DownloadReport {
+        let title = game.clean_title();
+        let mirrors = Mirrors::games(&self.config, game.game_type).with_reporter(self.reporter.clone());
+        let url = mirrors.file_url(&game.link);
+        let tmp_iso_folder = self.config.tmp_iso_folder_path();
+        let tmp_folder = game.output_folder(&tmp_iso_folder);
+        let mut plan = DownloadPlan {
+            decryptor: self.config.decryptor_path(),
+            external_download: self.config.external_iso_download,
+            ..Default::default()
+        };
+
+        let mut key = None;
+        if game.needs_decryption {
+            if let Err(e) = self.decryptor.validate_decryptor() {
+                plan.problems.push(e.to_string());
+            }
+
+            // The real run caches the keys list; the dry run fetches it without writing
+            let keys_cache_path = self.config.keys_folder_path().join("keys_cache.json");
+            if !keys_cache_path.exists() {
+                plan.actions.push(PlannedAction {
+                    action: PathAction::WriteFile,
+                    path: keys_cache_path,
+                    detail: "keys list cache".to_string(),
+                });
+            }
+            self.reporter.message("Looking up decryption key...");
+            key = KeyManager::new(&self.config)
+                .with_reporter(self.reporter.clone())
+                .without_cache_writes()
+                .find_key_for_game(game)
+                .await
+                .ok()
+                .flatten();
+            if key.is_none() {
+                plan.problems.push(format!("Could not find decryption key for game: {}", title));
+            }
+        }
 
-        let tmp_folder = self.config.tmp_iso_folder_path().join(game.clean_title());
-        fs::create_dir_all(&tmp_folder)?;
+        if !self.config.external_iso_download {
+            self.reporter.message("Checking remote file size...");
+            match mirrors.file_size(&game.link).await {
+                Ok(size) => plan.remote_size = size,
+                Err(e) => plan.problems.push(format!("Could not reach {}: {}", url, e)),
+            }
+        }
+        if let (Some(size), Some(free)) = (plan.remote_size, free_space(&tmp_iso_folder)) {
+            // The encrypted and decrypted ISOs exist side by side while decrypting, as do
+            // the ZIP and the extracted files of the other platforms
+            if free < size.saturating_mul(2) {
+                plan.problems.push(format!(
+                    "Not enough free space in {}: {} bytes free, about {} needed",
+                    tmp_iso_folder.display(),
+                    free,
+                    size.saturating_mul(2)
+                ));
+            }
+        }
+
+        let zip_path = tmp_folder.join(format!("{}.zip", title));
+        let encrypted_path = tmp_folder.join(format!("{}.iso", title));
+        let decrypted_path = tmp_folder.join(game.output_iso_filename());
+        let param_sfo_path = tmp_iso_folder.join("PARAM.SFO");
+
+        let action = |action, path: &Path, detail: &str| PlannedAction {
+            action,
+            path: path.to_path_buf(),
+            detail: detail.to_string(),
+        };
+        if tmp_folder.exists() {
+            let entries = fs::read_dir(&tmp_folder).map(|dir| dir.count()).unwrap_or(0);
+            plan.actions.push(action(
+                PathAction::DeleteDir,
+                &tmp_folder,
+                &format!("per-title temp folder is cleared first, {} entries inside", entries),
+            ));
+        }
+        plan.actions.extend([
+            action(PathAction::CreateDir, &tmp_folder, "per-title temp folder"),
+            action(
+                PathAction::WriteFile,
+                &zip_path,
+                if self.config.external_iso_download {
+                    "ZIP downloaded through the browser and copied here by hand"
+                } else {
+                    "downloaded ZIP"
+                },
+            ),
+        ]);
+        if !game.needs_decryption {
+            plan.actions.extend([
+                action(PathAction::WriteFile, &tmp_folder, "files extracted from the ZIP, kept as they are"),
+                action(PathAction::DeleteFile, &zip_path, "ZIP removed after extraction"),
+            ]);
+            let report = DownloadReport {
+                title,
+                url,
+                folder: tmp_folder,
+                plan: Some(plan),
+                ..Default::default()
+            };
+            self.print_plan(&report, game);
+            return report;
+        }
+        plan.actions.extend([
+            action(PathAction::WriteFile, &encrypted_path, "ISO extracted from the ZIP"),
+            action(PathAction::DeleteFile, &zip_path, "ZIP removed after extraction"),
+            action(PathAction::WriteFile, &decrypted_path, "decrypted ISO"),
+            action(PathAction::DeleteFile, &encrypted_path, "encrypted ISO removed after decryption"),
+            action(PathAction::WriteFile, &param_sfo_path, "PARAM.SFO extracted with 7z"),
+            action(PathAction::DeleteFile, &param_sfo_path, "PARAM.SFO removed after reading"),
+            action(
+                PathAction::Rename,
+                &decrypted_path,
+                &format!("to {} when PARAM.SFO can be read", tmp_folder.join("TITLE_ID-TITLE.iso").display()),
+            ),
+        ]);
+
+        let report = DownloadReport {
+            title,
+            url,
+            key: key.unwrap_or_default(),
+            folder: tmp_folder,
+            iso_name: Some(game.output_iso_filename()),
+            iso_path: Some(decrypted_path),
+            plan: Some(plan),
+            ..Default::default()
+        };
+        self.print_plan(&report, game);
+        report
+    }
+
+    /// Prints a dry-run plan through the reporter.
+    fn print_plan(&self, report: &DownloadReport, game: &Game) {
+        let Some(plan) = &report.plan else {
+            return;
+        };
+
+        self.reporter.message(format!("\nDry run for {}", report.title));
+        self.reporter.message(format!("  URL:         {}", report.url));
+        self.reporter.message(format!(
+            "  Remote size: {} (catalogue: {})",
+            plan.remote_size
+                .map(|size| format!("{} bytes", size))
+                .unwrap_or_else(|| "unknown".to_string()),
+            game.size
+        ));
+        if game.needs_decryption {
+            self.reporter.message(format!(
+                "  Key:         {}",
+                if report.key.is_empty() { "not found" } else { &report.key }
+            ));
+            self.reporter.message(format!("  Decryptor:   {}", plan.decryptor.display()));
+        } else {
+            self.reporter.message(format!("  Key:         not needed for {} games", game.game_type.name()));
+        }
+        if plan.external_download {
+            self.reporter.message("  Download:    manual, through the browser");
+        }
+
+        self.reporter.message("  Filesystem changes:");
+        for action in &plan.actions {
+            self.reporter.message(format!(
+                "    {:<10} {} ({})",
+                action.action.label(),
+                action.path.display(),
+                action.detail
+            ));
+        }
+
+        if plan.is_ready() {
+            self.reporter.message("  No problems found.");
+        } else {
+            self.reporter.message("  Problems:");
+            for problem in &plan.problems {
+                self.reporter.message(format!("    - {}", problem));
+            }
+        }
+        self.reporter.message("Dry run: nothing was downloaded or written.");
+    }
+
+    /// Download, extract, and decrypt the file, handling both direct and external download methods.
+    /// Without a key (platforms that need no decryption) the extracted files are kept as they are.
+    async fn download_extract_and_decrypt(&self, game: &Game, key: Option<&str>) -> Result<DownloadReport> {
+        self.reporter.message(format!(" # {} ISO file...", game.game_type.name()));
+
+        // The browser is sent to the first mirror; direct downloads fail over between them.
+        // Files on a local mirror need no browser, and offline there is none to send it to.
+        let mirrors = Mirrors::games(&self.config, game.game_type);
+        let link = mirrors.file_url(&game.link);
+        let use_browser =
+            self.config.external_iso_download && !self.config.offline && mirrors.local_file(&game.link).is_none();
+
+        let tmp_folder = game.output_folder(&self.config.tmp_iso_folder_path());
+        let mut report = DownloadReport {
+            title: game.clean_title(),
+            url: link.to_string(),
+            key: key.unwrap_or_default().to_string(),
+            folder: tmp_folder.clone(),
+            ..Default::default()
+        };
+        fs::create_dir_all(&tmp_folder).map_err(|e| Error::filesystem("create", &tmp_folder, e))?;
 
         // --- NEW: clear temp folder to avoid leftovers from previous games ---
         if tmp_folder.exists() {
-            fs::remove_dir_all(&tmp_folder)?;
+            fs::remove_dir_all(&tmp_folder).map_err(|e| Error::filesystem("remove", &tmp_folder, e))?;
         }
-        fs::create_dir_all(&tmp_folder)?;
+        fs::create_dir_all(&tmp_folder).map_err(|e| Error::filesystem("create", &tmp_folder, e))?;
         // -------------------------------------------------------------
 
         let decrypted_file_name = game.output_iso_filename();
@@ -141,8 +510,11 @@ impl Downloader {
 
         // Skip download if file already exists
         if decrypted_file_path.exists() {
-            println!(" - File previously downloaded and decrypted :)\n");
-            return Ok(());
+            self.reporter.message(" - File previously downloaded and decrypted :)\n");
+            report.iso_bytes = file_size(&decrypted_file_path);
+            report.iso_name = Some(decrypted_file_name);
+            report.iso_path = Some(decrypted_file_path);
+            return Ok(report);
         }
 
         let new_file_name = format!("{}.zip", game.clean_title());
@@ -150,14 +522,38 @@ impl Downloader {
         let encrypted_file_name = format!("{}.iso", game.clean_title());
         let encrypted_file_path = tmp_folder.join(&encrypted_file_name);
 
-        if self.config.external_iso_download {
-            self.download_using_navigator(link, &new_file_name, &tmp_file, &encrypted_file_name)
+        let step = Instant::now();
+        if use_browser {
+            self.download_using_navigator(&link, &new_file_name, &tmp_file, &encrypted_file_name)
                 .await?;
         } else {
-            self.download_using_request(link, &tmp_file).await?;
+            self.download_using_request(game, &tmp_file).await?;
+        }
+        report.download_secs = step.elapsed().as_secs_f64();
+        report.zip_bytes = file_size(&tmp_file);
+
+        // Listings that give the ZIP's hash (Internet Archive items) let the download be checked
+        if let Some(expected) = game.zip_sha1.as_deref().filter(|_| tmp_file.exists()) {
+            self.check_zip_sha1(&tmp_file, expected)?;
         }
 
         // Unzip and clean up
+        let step = Instant::now();
+        let Some(key) = key else {
+            if tmp_file.exists() {
+                self.unzip_file(&tmp_file).await?;
+                self.remove_file(&tmp_file)?;
+            }
+            report.extract_secs = step.elapsed().as_secs_f64();
+            // CUE/BIN and ISO layouts are kept; the main file is recorded as the download
+            if let Some(path) = find_downloaded_iso(&self.config, game) {
+                report.iso_bytes = file_size(&path);
+                report.iso_name = path.file_name().map(|n| n.to_string_lossy().to_string());
+                report.iso_path = Some(path);
+            }
+            self.reporter.message(" ");
+            return Ok(report);
+        };
         if tmp_file.exists() {
             self.unzip_file(&tmp_file).await?;
             self.remove_file(&tmp_file)?;
@@ -170,12 +566,12 @@ impl Downloader {
                     if path.extension() == Some(OsStr::new("iso")) {
                         if path != encrypted_file_path {
                             if let Err(e) = fs::rename(&path, &encrypted_file_path) {
-                                println!(
+                                self.reporter.message(format!(
                                     "Error renaming extracted ISO: {} -> {}: {}",
                                     path.display(),
                                     encrypted_file_path.display(),
                                     e
-                                );
+                                ));
                             }
                         }
                         break;
@@ -184,7 +580,11 @@ impl Downloader {
             }
         }
 
+        report.extract_secs = step.elapsed().as_secs_f64();
+        report.encrypted_bytes = file_size(&encrypted_file_path);
+
         // Decrypt the extracted ISO with the key
+        let step = Instant::now();
         if encrypted_file_path.exists() {
             self.decryptor
                 .decrypt_iso(&encrypted_file_path, &decrypted_file_path, key)
@@ -192,21 +592,25 @@ impl Downloader {
             self.remove_file(&encrypted_file_path)?;
 
             // Rename ISO using PARAM.SFO with fallback
-            self.rename_iso_with_param_sfo(&decrypted_file_path)?;
+            let iso_path = self.rename_iso_with_param_sfo(&decrypted_file_path)?;
+            report.iso_bytes = file_size(&iso_path);
+            report.iso_name = iso_path.file_name().map(|n| n.to_string_lossy().to_string());
+            report.iso_path = Some(iso_path);
         }
+        report.decrypt_secs = step.elapsed().as_secs_f64();
 
-        println!(" ");
-        Ok(())
+        self.reporter.message(" ");
+        Ok(report)
     }
 
     /// Extracts TITLE_ID and TITLE from decrypted ISO and renames the file.
-    /// Falls back to old naming if PARAM.SFO can't be read.
-    fn rename_iso_with_param_sfo(&self, iso_path: &Path) -> Result<()> {
+    /// Falls back to old naming if PARAM.SFO can't be read. Returns the final ISO path.
+    fn rename_iso_with_param_sfo(&self, iso_path: &Path) -> Result<PathBuf> {
         let tmp_folder = self.config.tmp_iso_folder_path();
         let param_sfo_path = tmp_folder.join("PARAM.SFO");
 
         // Try to extract PARAM.SFO with 7z
-        let status = std::process::Command::new("7z")
+        let output = std::process::Command::new("7z")
             .args([
                 "e",
                 iso_path.to_str().unwrap(),
@@ -214,27 +618,32 @@ impl Downloader {
                 &format!("-o{}", tmp_folder.display()),
                 "-y",
             ])
-            .status();
+            .output();
 
-        if let Ok(status) = status {
-            if !status.success() || !param_sfo_path.exists() {
-                println!("⚠️ Could not extract PARAM.SFO, keeping original filename.");
-                return Ok(()); // fallback
+        if let Ok(output) = output {
+            if !output.status.success() || !param_sfo_path.exists() {
+                self.reporter.message("⚠️ Could not extract PARAM.SFO, keeping original filename.");
+                return Ok(iso_path.to_path_buf()); // fallback
             }
         } else {
-            println!("⚠️ Failed to run 7z, keeping original filename.");
-            return Ok(()); // fallback
+            self.reporter.message("⚠️ Failed to run 7z, keeping original filename.");
+            return Ok(iso_path.to_path_buf()); // fallback
         }
 
         // Try parsing PARAM.SFO
         let mut buf = Vec::new();
-        File::open(&param_sfo_path)?.read_to_end(&mut buf)?;
+        File::open(&param_sfo_path)
+            .and_then(|mut file| file.read_to_end(&mut buf))
+            .map_err(|e| Error::ParamSfo {
+                path: param_sfo_path.clone(),
+                reason: e.to_string(),
+            })?;
         let param = match sfo::Sfo::from_bytes(&buf) {
             Some(p) => p,
             None => {
-                println!("⚠️ Invalid PARAM.SFO, keeping original filename.");
+                self.reporter.message("⚠️ Invalid PARAM.SFO, keeping original filename.");
                 let _ = fs::remove_file(&param_sfo_path);
-                return Ok(()); // fallback
+                return Ok(iso_path.to_path_buf()); // fallback
             }
         };
 
@@ -250,161 +659,181 @@ impl Downloader {
         let new_path = iso_path.parent().unwrap().join(&new_name);
 
         if iso_path != new_path {
-            fs::rename(&iso_path, &new_path)?;
-            println!("✅ Renamed ISO to {}", new_path.display());
+            fs::rename(iso_path, &new_path).map_err(|e| Error::filesystem("rename", iso_path, e))?;
+            self.reporter.message(format!("✅ Renamed ISO to {}", new_path.display()));
         }
 
         let _ = fs::remove_file(&param_sfo_path);
-        Ok(())
+        Ok(new_path)
     }
 
-    /// Downloads a file using reqwest, supporting resume and progress bar.
-    /// Retries on failure up to max_retries.
-    async fn download_using_request(&self, link: &str, file_path: &Path) -> Result<()> {
-        let total_size = self.get_file_size(link).await?;
+    /// Downloads a listed file from the game mirrors, supporting resume and progress bar.
+    /// Retries on failure up to max_retries; a retry moves on to the next mirror and
+    /// resumes from the bytes already written.
+    async fn download_using_request(&self, game: &Game, file_path: &Path) -> Result<()> {
+        let link = game.link.as_str();
+        let mirrors = Mirrors::games(&self.config, game.game_type).with_reporter(self.reporter.clone());
+        if let Some(source_path) = mirrors.local_file(link) {
+            return self.copy_local_file(&source_path, file_path);
+        }
+        let total_size = mirrors.file_size(link).await?;
         let mut retries = 0;
+        let mut mirror = 0;
 
         while retries < self.config.max_retries {
-            let mut headers = reqwest::header::HeaderMap::new();
             let mut first_byte = 0;
 
-            if let Some(size) = total_size {
-                if file_path.exists() {
-                    first_byte = fs::metadata(file_path)?.len();
-                    if first_byte >= size {
-                        println!(
-                            "The file {} was downloaded previously.",
-                            file_path.display()
-                        );
-                        return Ok(());
-                    }
+            // A partial file is resumed even when the mirror does not report the size
+            if file_path.exists() {
+                first_byte = fs::metadata(file_path)?.len();
+                if total_size.is_some_and(|size| first_byte >= size) {
+                    self.reporter.message(format!(
+                        "The file {} was downloaded previously.",
+                        file_path.display()
+                    ));
+                    return Ok(());
                 }
-                headers.insert(
-                    "Range",
-                    format!("bytes={}-{}", first_byte, size - 1).parse()?,
-                );
             }
 
-            // Print the message before creating the progress bar
-            println!("Attempting download from: {}", link);
-            std::io::stdout().flush().ok();
-            let progress_bar = if let Some(total) = total_size {
-                let pb = ProgressBar::new(total);
-                pb.set_style(
-                    ProgressStyle::default_bar()
-                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                        .unwrap()
-                        .progress_chars("#>-")
-                );
-                pb.set_draw_target(indicatif::ProgressDrawTarget::stdout());
-                std::io::stdout().flush().ok();
-                Some(pb)
-            } else {
-                None
-            };
-
-            let client = reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(
-                    self.config.timeout_request.unwrap_or(1800), // Longer timeout for PS3 files
-                ))
-                .connect_timeout(std::time::Duration::from_secs(30))
-                .build()?;
-
-            match client.get(link).headers(headers).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        // Open file for append and seek to the correct position
-                        let mut file = OpenOptions::new()
-                            .create(true)
-                            .append(false)
-                            .write(true)
-                            .open(file_path)
-                            .await?;
-                        file.seek(SeekFrom::Start(first_byte)).await?;
-                        // Use the new streaming API for reqwest 0.12
-                        let mut stream = response.bytes_stream();
-
-                        let mut downloaded = first_byte;
-                        let mut error_occurred = false;
-                        while let Some(chunk_result) = stream.next().await {
-                            match chunk_result {
-                                Ok(chunk) => {
-                                    file.write_all(&chunk).await?;
-                                    downloaded += chunk.len() as u64;
-                                    if let Some(pb) = &progress_bar {
-                                        pb.set_position(downloaded);
-                                    }
-                                }
-                                Err(e) => {
-                                    if let Some(pb) = &progress_bar {
-                                        pb.println(format!("Error during download: {}", e));
-                                    } else {
-                                        println!("Error during download: {}", e);
-                                    }
-                                    error_occurred = true;
-                                    break;
+            match mirrors.open_file(link, first_byte, mirror).await {
+                Ok((index, remote)) => {
+                    mirror = index;
+                    // Print the message before creating the progress bar
+                    self.reporter.message(format!("Attempting download from: {}", remote.url));
+                    let progress_bar = total_size.map(|total| {
+                        self.reporter.bar(
+                            Phase::Download,
+                            Some(total),
+                            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                        )
+                    });
+
+                    // A mirror that cannot resume sends the file from its start, dropping what was written
+                    let mut file = OpenOptions::new()
+                        .create(true)
+                        .append(false)
+                        .truncate(false)
+                        .write(true)
+                        .open(file_path)
+                        .await?;
+                    file.set_len(remote.offset).await?;
+                    file.seek(SeekFrom::Start(remote.offset)).await?;
+                    let expected_size = total_size.or(remote.total_size);
+                    let mut stream = remote.stream;
+
+                    let mut downloaded = remote.offset;
+                    let mut error_occurred = None;
+                    while let Some(chunk_result) = stream.next().await {
+                        match chunk_result {
+                            Ok(chunk) => {
+                                file.write_all(&chunk).await?;
+                                downloaded += chunk.len() as u64;
+                                if let Some(pb) = &progress_bar {
+                                    pb.set_position(downloaded);
                                 }
                             }
-                        }
-                        if let Some(pb) = &progress_bar {
-                            if let Some(length) = pb.length() {
-                                if pb.position() >= length {
-                                    pb.finish_with_message("Download completed");
+                            Err(e) => {
+                                if let Some(pb) = &progress_bar {
+                                    pb.println(format!("Error during download: {:#}", e));
                                 } else {
-                                    pb.finish_with_message("Download incomplete");
+                                    self.reporter.message(format!("Error during download: {:#}", e));
                                 }
-                            } else {
-                                pb.finish_with_message("Download completed");
+                                error_occurred = Some(format!("{:#}", e));
+                                break;
                             }
                         }
-                        std::io::stdout().flush().ok();
-                        if let Some(pb) = progress_bar {
-                            drop(pb);
+                    }
+                    // A stream that ends early is retried from the next mirror
+                    if error_occurred.is_none() {
+                        if let Some(size) = expected_size.filter(|&size| downloaded < size) {
+                            error_occurred =
+                                Some(format!("connection closed after {} of {} bytes", downloaded, size));
                         }
-                        if error_occurred {
-                            retries += 1;
-                            if retries < self.config.max_retries {
-                                println!(
-                                    "Waiting {} seconds before retry...",
-                                    self.config.delay_between_retries
-                                );
-                                tokio::time::sleep(tokio::time::Duration::from_secs(
-                                    self.config.delay_between_retries,
-                                ))
-                                .await;
+                    }
+                    // Written bytes must reach the disk before the file is resumed or unzipped
+                    file.flush().await?;
+                    if let Some(pb) = &progress_bar {
+                        if let Some(length) = pb.length() {
+                            if pb.position() >= length {
+                                pb.finish_with_message("Download completed");
+                            } else {
+                                pb.abandon_with_message("Download incomplete");
                             }
-                            continue;
+                        } else {
+                            pb.finish_with_message("Download completed");
                         }
-                        break;
-                    } else {
-                        println!(
-                            "HTTP error: {} - {}",
-                            response.status(),
-                            response.status().as_str()
-                        );
+                    }
+                    std::io::stdout().flush().ok();
+                    if let Some(pb) = progress_bar {
+                        drop(pb);
+                    }
+                    if let Some(reason) = error_occurred {
                         retries += 1;
+                        self.report_retry(retries, reason);
+                        mirror = (mirror + 1) % mirrors.len();
+                        if retries < self.config.max_retries {
+                            self.reporter.message(format!(
+                                "Waiting {} seconds before retry...",
+                                self.config.delay_between_retries
+                            ));
+                            tokio::time::sleep(tokio::time::Duration::from_secs(
+                                self.config.delay_between_retries,
+                            ))
+                            .await;
+                        }
+                        continue;
                     }
+                    break;
                 }
                 Err(e) => {
-                    println!(
-                        "Request error (attempt {}/{}): {}",
+                    self.reporter.message(format!(
+                        "Request error (attempt {}/{}): {:#}",
                         retries + 1,
                         self.config.max_retries,
                         e
-                    );
+                    ));
                     retries += 1;
+                    self.report_retry(retries, format!("{:#}", e));
                 }
             }
         }
         if retries == self.config.max_retries {
-            anyhow::bail!(
-                "Failed to download file after {} attempts.",
-                self.config.max_retries
-            );
+            return Err(Error::Http {
+                url: mirrors.file_url(link),
+                reason: format!("failed to download file after {} attempts", self.config.max_retries),
+            }
+            .into());
         }
         Ok(())
     }
 
+    /// "Downloads" a file of a local mirror: hardlinks it into place, or copies it when the
+    /// mirror is on another filesystem.
+    fn copy_local_file(&self, source_path: &Path, file_path: &Path) -> Result<()> {
+        if file_path.exists() {
+            fs::remove_file(file_path).map_err(|e| Error::filesystem("remove", file_path, e))?;
+        }
+        if fs::hard_link(source_path, file_path).is_ok() {
+            self.reporter.message(format!("Linked {}", source_path.display()));
+            return Ok(());
+        }
+        self.reporter.message(format!("Copying {}...", source_path.display()));
+        fs::copy(source_path, file_path).map_err(|e| Error::filesystem("copy", source_path, e))?;
+        Ok(())
+    }
+
+    /// Emits a retry event for a failed download attempt (`attempt` is 1-based).
+    fn report_retry(&self, attempt: u32, reason: String) {
+        if attempt < self.config.max_retries {
+            self.reporter.send(ProgressEvent::Retry {
+                phase: Phase::Download,
+                attempt,
+                max_attempts: self.config.max_retries,
+                reason,
+            });
+        }
+    }
+
     /// Prompts the user to download the file manually using a browser.
     async fn download_using_navigator(
         &self,
@@ -415,83 +844,57 @@ impl Downloader {
     ) -> Result<()> {
         let destination_folder = self.config.tmp_iso_folder_path();
 
-        println!("Opening browser with download link ({})", route);
+        self.reporter.message(format!("Opening browser with download link ({})", route));
         open::that(route)?;
 
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-        println!(
+        self.reporter.message(format!(
             "Please download the file and copy '{}' to '{}'",
             downloaded_file_name,
             destination_folder.display()
-        );
+        ));
         self.open_explorer(&destination_folder);
 
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-        println!("Waiting for the file to be copied...");
+        self.reporter.message("Waiting for the file to be copied...");
 
         let mut input = String::new();
         std::io::stdin().read_line(&mut input)?;
 
         // Wait until the file is present
         while !zip_file.exists() && !destination_folder.join(encrypted_file).exists() {
-            println!(
-                "\nFile not found!! Make sure to download and copy the file to '{}'",
+            self.reporter.message(format!(
+                "File not found!! Make sure to download and copy the file to '{}'",
                 destination_folder.display()
-            );
+            ));
             let mut input = String::new();
             std::io::stdin().read_line(&mut input)?;
         }
 
-        println!();
         Ok(())
     }
 
-    /// Gets the file size from the server using a range request or content-length.
-    async fn get_file_size(&self, link: &str) -> Result<Option<u64>> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .build()?;
-
-        let response = client.get(link).header("Range", "bytes=0-1").send().await?;
-
-        if let Some(range_header) = response.headers().get("content-range") {
-            if let Ok(range_str) = range_header.to_str() {
-                if let Some(total_str) = range_str.split('/').nth(1) {
-                    if let Ok(total_size) = total_str.parse::<u64>() {
-                        return Ok(Some(total_size));
-                    }
-                }
-            }
-        }
-
-        // Try to get content-length as fallback
-        if let Some(content_length) = response.headers().get("content-length") {
-            if let Ok(length_str) = content_length.to_str() {
-                if let Ok(total_size) = length_str.parse::<u64>() {
-                    return Ok(Some(total_size));
-                }
-            }
-        }
-
-        Ok(None)
-    }
-
     /// Unzips the downloaded file, showing a progress bar if possible.
     async fn unzip_file(&self, zip_path: &Path) -> Result<()> {
-        use indicatif::ProgressDrawTarget;
-        println!("Extracting ZIP file...");
-        std::io::stdout().flush().ok();
+        self.reporter.message("Extracting ZIP file...");
         let dest = zip_path.parent().unwrap();
         let file_size = fs::metadata(zip_path)?.len();
         if file_size == 0 {
-            anyhow::bail!("ZIP file is empty (0 bytes)");
+            return Err(Error::Zip {
+                path: zip_path.to_path_buf(),
+                reason: "file is empty (0 bytes)".to_string(),
+            }
+            .into());
         }
-        let file = fs::File::open(zip_path)?;
+        let file = fs::File::open(zip_path).map_err(|e| Error::filesystem("open", zip_path, e))?;
         let mut archive = match ZipArchive::new(file) {
             Ok(archive) => archive,
             Err(e) => {
-                anyhow::bail!("Invalid ZIP archive: {}. The file may be corrupted or incomplete. Try downloading again.", e);
+                return Err(Error::Zip {
+                    path: zip_path.to_path_buf(),
+                    reason: e.to_string(),
+                }
+                .into());
             }
         };
         let total_files = archive.len();
@@ -506,18 +909,12 @@ impl Downloader {
                 file_sizes.push(0);
             }
         }
-        std::io::stdout().flush().ok();
         if total_size > 0 {
-            let progress_bar = ProgressBar::new(total_size);
-            progress_bar.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.green} Extracting: [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                    .unwrap()
-                    .progress_chars("#>-")
+            let progress_bar = self.reporter.bar(
+                Phase::Extract,
+                Some(total_size),
+                "{spinner:.green} Extracting: [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
             );
-            progress_bar.set_draw_target(ProgressDrawTarget::stdout());
-            progress_bar.tick();
-            std::io::stdout().flush().ok();
             for i in 0..archive.len() {
                 let mut file = archive.by_index(i)?;
                 let outpath = dest.join(file.name());
@@ -545,16 +942,11 @@ impl Downloader {
             std::io::stdout().flush().ok();
         } else {
             // Always show a progress bar based on file count if size is unknown
-            let progress_bar = ProgressBar::new(total_files as u64);
-            progress_bar.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.green} Extracting: [{bar:40.cyan/blue}] {pos}/{len} files ({eta})")
-                    .unwrap()
-                    .progress_chars("#>-")
+            let progress_bar = self.reporter.bar(
+                Phase::Extract,
+                Some(total_files as u64),
+                "{spinner:.green} Extracting: [{bar:40.cyan/blue}] {pos}/{len} files ({eta})",
             );
-            progress_bar.set_draw_target(ProgressDrawTarget::stdout());
-            progress_bar.tick();
-            std::io::stdout().flush().ok();
             for i in 0..archive.len() {
                 let mut file = archive.by_index(i)?;
                 let outpath = dest.join(file.name());
@@ -584,13 +976,40 @@ impl Downloader {
         Ok(())
     }
 
+    /// Compares the SHA-1 of the downloaded ZIP with the listed one. A mismatch is only
+    /// reported: a mirror may hold a differently packed ZIP of the same dump, and a
+    /// corrupt one fails to extract anyway.
+    fn check_zip_sha1(&self, zip_path: &Path, expected: &str) -> Result<()> {
+        self.reporter.message("Checking ZIP SHA-1...");
+        let mut file = File::open(zip_path).map_err(|e| Error::filesystem("open", zip_path, e))?;
+        let mut hasher = Sha1::new();
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = file.read(&mut buffer).map_err(|e| Error::filesystem("read", zip_path, e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let actual: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+        if actual == expected {
+            self.reporter.message("ZIP SHA-1 matches the listing");
+        } else {
+            self.reporter.message(format!(
+                "Warning: ZIP SHA-1 is {} but the listing gives {}",
+                actual, expected
+            ));
+        }
+        Ok(())
+    }
+
     /// Removes a file, printing an error if it fails.
     fn remove_file(&self, file_path: &Path) -> Result<()> {
         // Attempt to remove the file; log error if it fails
         match fs::remove_file(file_path) {
             Ok(_) => Ok(()),
             Err(e) => {
-                println!("Error removing {}: {}", file_path.display(), e);
+                self.reporter.message(format!("Error removing {}: {}", file_path.display(), e));
                 Ok(())
             }
         }
@@ -599,7 +1018,12 @@ impl Downloader {
     /// Opens the file explorer at the given path.
     fn open_explorer(&self, path: &Path) {
         if let Err(e) = open::that(path) {
-            println!("Error opening {}: {}", path.display(), e);
+            self.reporter.message(format!("Error opening {}: {}", path.display(), e));
         }
     }
 }
+
+/// Returns the size of a file in bytes, if it exists.
+fn file_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|m| m.len())
+}
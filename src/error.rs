@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error as ThisError;
+
+/// Exit code for failures without a more specific kind (e.g. bad config or no matching game).
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Failures that callers and wrapper scripts need to tell apart.
+/// Functions still return `anyhow::Result`; these are carried inside it and
+/// each kind maps to its own process exit code (see `exit_code`).
+#[derive(Debug, ThisError)]
+pub enum Error {
+    /// The game list could not be fetched or parsed
+    #[error("Failed to fetch {platform} game list from {url}: {reason}")]
+    CatalogFetch {
+        platform: &'static str,
+        url: String,
+        reason: String,
+    },
+    /// The keys list has no key for the game, or the key file holds none
+    #[error("Could not find decryption key for game: {title}. The game may not be available or the key may not exist.")]
+    KeyNotFound { title: String },
+    /// A request failed or the server answered with an error status
+    #[error("HTTP request to {url} failed: {reason}")]
+    Http { url: String, reason: String },
+    /// The downloaded ZIP is empty, truncated or corrupt
+    #[error("Invalid ZIP archive {}: {reason}. The file may be corrupted or incomplete. Try downloading again.", path.display())]
+    Zip { path: PathBuf, reason: String },
+    #[error("PS3Dec binary not found at: {}. Please build the PS3Dec program first:\n\
+             cd decryptor/PS3Dec && mkdir -p build && cd build && cmake .. && make", path.display())]
+    DecryptorMissing { path: PathBuf },
+    #[error("PS3Dec binary is not executable: {0}. Please make it executable with 'chmod +x {0}'", path.display())]
+    DecryptorNotExecutable { path: PathBuf },
+    #[error("Decryption timed out after {seconds} seconds")]
+    DecryptionTimeout { seconds: u64 },
+    /// PS3Dec could not be started, exited with an error or wrote no output
+    #[error("Decryption failed: {reason}")]
+    Decryption { reason: String },
+    /// PARAM.SFO was extracted but could not be read
+    #[error("Failed to read PARAM.SFO {}: {reason}", path.display())]
+    ParamSfo { path: PathBuf, reason: String },
+    /// A file or folder could not be created, read, written, renamed or removed
+    #[error("Failed to {action} {}: {source}", path.display())]
+    Filesystem {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// `--offline` is set and the resource is only available over the network
+    #[error("Offline mode: not fetching {url}; only caches and local sources are used")]
+    Offline { url: String },
+    /// The catalogue database could not be opened, read or written
+    #[error("Catalogue database {} failed: {source}", path.display())]
+    Database {
+        path: PathBuf,
+        #[source]
+        source: rusqlite::Error,
+    },
+}
+
+impl Error {
+    /// Wraps an I/O error with what was being done to which path, e.g. `("create", folder)`.
+    pub fn filesystem(action: &'static str, path: &Path, source: std::io::Error) -> Self {
+        Self::Filesystem {
+            action,
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Wraps a SQLite error with the database path.
+    pub fn database(path: &Path, source: rusqlite::Error) -> Self {
+        Self::Database {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Returns the process exit code for this kind of failure.
+    /// 0 is success, 1 is any other failure and 2 is a command line usage error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::CatalogFetch { .. } => 3,
+            Error::KeyNotFound { .. } => 4,
+            Error::Http { .. } => 5,
+            Error::Zip { .. } => 6,
+            Error::DecryptorMissing { .. } => 7,
+            Error::DecryptorNotExecutable { .. } => 8,
+            Error::DecryptionTimeout { .. } => 9,
+            Error::Decryption { .. } => 10,
+            Error::ParamSfo { .. } => 11,
+            Error::Filesystem { .. } => 12,
+            Error::Database { .. } => 13,
+            Error::Offline { .. } => 14,
+        }
+    }
+
+    /// Returns a stable snake_case name of the kind, as used in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::CatalogFetch { .. } => "catalog_fetch",
+            Error::KeyNotFound { .. } => "key_not_found",
+            Error::Http { .. } => "http",
+            Error::Zip { .. } => "zip",
+            Error::DecryptorMissing { .. } => "decryptor_missing",
+            Error::DecryptorNotExecutable { .. } => "decryptor_not_executable",
+            Error::DecryptionTimeout { .. } => "decryption_timeout",
+            Error::Decryption { .. } => "decryption",
+            Error::ParamSfo { .. } => "param_sfo",
+            Error::Filesystem { .. } => "filesystem",
+            Error::Database { .. } => "database",
+            Error::Offline { .. } => "offline",
+        }
+    }
+}
+
+/// Returns the kind and exit code of any error. A typed `Error` anywhere in the chain wins;
+/// otherwise untyped HTTP, ZIP and I/O errors from dependencies are classified as such.
+pub fn classify(error: &anyhow::Error) -> (&'static str, u8) {
+    if let Some(error) = error.chain().find_map(|cause| cause.downcast_ref::<Error>()) {
+        return (error.kind(), error.exit_code());
+    }
+    for cause in error.chain() {
+        if cause.is::<reqwest::Error>() {
+            return ("http", 5);
+        }
+        if cause.is::<zip::result::ZipError>() {
+            return ("zip", 6);
+        }
+        if cause.is::<std::io::Error>() {
+            return ("filesystem", 12);
+        }
+        if cause.is::<rusqlite::Error>() {
+            return ("database", 13);
+        }
+    }
+    ("other", EXIT_FAILURE)
+}
+
+/// Returns the process exit code for any error.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    classify(error).1
+}
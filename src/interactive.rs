@@ -0,0 +1,127 @@
+use crate::{
+    details::{describe_free_space, print_game_details, GameDetails},
+    display::print_games,
+    downloader::Downloader,
+    models::Game,
+    prompt::Prompt,
+    query::query_games,
+    queue::{download_queue, print_queue_summary},
+    selection::parse_selection,
+    utils::format_size,
+};
+use anyhow::Result;
+
+/// Main interactive loop for searching and downloading games.
+/// The search prompt accepts search words and filters (see `query`) and supports line editing, history (Up/Down, Ctrl-R) and Tab completion of title words.
+pub async fn run_main_loop(
+    downloader: &Downloader,
+    games: Vec<Game>,
+) -> Result<()> {
+    let mut prompt = Prompt::new(&games)?;
+    loop {
+        let input = prompt
+            .read_line("Find title to download (leave empty to exit): ")?
+            .unwrap_or_default();
+        let search_input = input.trim();
+
+        if search_input.is_empty() {
+            println!("Exiting...");
+            break Ok(());
+        }
+        prompt.remember(search_input);
+
+        let filtered_games: Vec<&Game> = match query_games(downloader.config(), &games, search_input) {
+            Ok(matches) => matches.into_iter().map(|hit| hit.game).collect(),
+            Err(e) => {
+                println!("{}\n", e);
+                continue;
+            }
+        };
+
+        if filtered_games.is_empty() {
+            println!("No games found\n");
+            continue;
+        }
+
+        print_games(&filtered_games);
+
+        let Some(input) = prompt.read_line(&format!(
+            "Enter title numbers [1-{}] (e.g. 1,3,5-9 or all): ",
+            filtered_games.len()
+        ))?
+        else {
+            println!("Exiting...");
+            break Ok(());
+        };
+
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        let selected_games: Vec<&Game> = match parse_selection(&input, filtered_games.len()) {
+            Ok(indexes) => indexes.into_iter().map(|i| filtered_games[i]).collect(),
+            Err(e) => {
+                println!("{}\n", e);
+                println!("Please try again..."); // Added user prompt for better UX
+                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                continue;
+            }
+        };
+
+        // Show what is about to happen before starting a multi-gigabyte download
+        let details: Vec<GameDetails> = selected_games
+            .iter()
+            .map(|game| GameDetails::collect(downloader.config(), game))
+            .collect();
+        let question = if let [single] = details.as_slice() {
+            print_game_details(single);
+            format!("\nDownload {}? [y/N]: ", single.title)
+        } else {
+            print_selection(&details);
+            format!("Download these {} games? [y/N]: ", details.len())
+        };
+        let answer = prompt.read_line(&question)?.unwrap_or_default();
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Cancelled\n");
+            continue;
+        }
+
+        let results = download_queue(downloader, &selected_games).await;
+        if results.len() > 1 {
+            print_queue_summary(&results);
+        }
+    }
+}
+
+/// Prints the selected games with their key and download state, their combined size and the free space.
+fn print_selection(games: &[GameDetails]) {
+    println!("\nSelected:");
+    for game in games {
+        let mut notes = Vec::new();
+        if game.key_cached == Some(false) {
+            notes.push("no key");
+        }
+        if game.existing_iso.is_some() {
+            notes.push("already downloaded");
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", notes.join(", "))
+        };
+        println!("  {} ({}){}", game.title, game.size, notes);
+    }
+
+    let total: u64 = games.iter().filter_map(|game| game.size_bytes).sum();
+    let unknown = games.iter().filter(|game| game.size_bytes.is_none()).count();
+    if unknown > 0 {
+        println!("Total size: {} (+{} of unknown size)", format_size(total), unknown);
+    } else {
+        println!("Total size: {}", format_size(total));
+    }
+
+    // Games are processed one at a time, so only the largest one needs room for a second copy
+    let largest = games.iter().filter_map(|game| game.size_bytes).max().unwrap_or(0);
+    let free = games.first().and_then(|game| game.free_space);
+    println!("Free space: {}\n", describe_free_space(free, Some(total + largest)));
+}
@@ -1,14 +1,40 @@
-use crate::{config::Config, models::Game};
+use crate::{
+    catalog::Catalog,
+    config::Config,
+    error::Error,
+    listing::{file_link, parse_listing, Fetched, Validators},
+    models::Game,
+    progress::Reporter,
+    source::Mirrors,
+    utils::{is_expired, unix_now, write_atomic},
+    watchlist::{check_watchlist, report_alerts, AlertReason},
+};
 use anyhow::Result;
-use reqwest;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
 use std::io::Read;
 
+/// When the keys cache was last checked against the website, and the validators to check
+/// it with next time. Stored next to the cache as `keys_cache.meta.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct KeysCacheMeta {
+    /// Seconds since the Unix epoch
+    checked_at: u64,
+    /// Mirror the cached list was fetched from, which issued the validators
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(flatten)]
+    validators: Validators,
+}
+
 /// KeyManager handles downloading and managing PS3 decryption keys.
 pub struct KeyManager {
     config: Config,
+    reporter: Reporter,
+    /// Whether a fetched keys list is written to the cache
+    save_cache: bool,
 }
 
 impl KeyManager {
@@ -16,135 +42,219 @@ impl KeyManager {
     pub fn new(config: &Config) -> Self {
         Self {
             config: config.clone(),
+            reporter: Reporter::console(),
+            save_cache: true,
         }
     }
 
+    /// Routes status lines through the given reporter.
+    pub fn with_reporter(mut self, reporter: Reporter) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Never writes the keys cache, e.g. for dry runs that must not touch the disk.
+    pub fn without_cache_writes(mut self) -> Self {
+        self.save_cache = false;
+        self
+    }
+
     /// Downloads and caches the PS3 keys list.
+    /// The cached list is checked for changes once it is older than `CACHE_TTL_HOURS`.
     pub async fn download_keys_list(&self) -> Result<HashMap<String, String>> {
-        let keys_cache_path = self.config.keys_folder_path().join("keys_cache.json");
-        
         // Try to load from cache first
-        if keys_cache_path.exists() {
-            if let Ok(keys) = self.load_keys_from_cache(&keys_cache_path) {
-                println!("Loaded {} PS3 keys from cache", keys.len());
+        if let Some(keys) = self.cached_keys() {
+            let checked_at = self.load_cache_meta().map(|meta| meta.checked_at);
+            // Offline with only web mirrors, the cached list is all there is
+            let reachable = !Mirrors::keys(&self.config).is_empty();
+            if !reachable || !is_expired(checked_at, self.config.cache_ttl()) {
+                self.reporter.message(format!("Loaded {} PS3 keys from cache", keys.len()));
                 return Ok(keys);
             }
+            // An outdated list is better than none while the website cannot be reached
+            return match self.refresh_keys_list().await {
+                Ok(keys) => Ok(keys),
+                Err(e) => {
+                    self.reporter.message(format!(
+                        "Could not refresh the PS3 keys list, using the cached one: {:#}",
+                        e
+                    ));
+                    Ok(keys)
+                }
+            };
         }
 
         // Fetch from web if cache doesn't exist or is invalid
-        println!("Fetching PS3 keys list from Redump...");
-        let keys = self.fetch_keys_from_web().await?;
+        self.refresh_keys_list().await
+    }
+
+    /// Fetches the PS3 keys list from the web and overwrites the cache. The request is
+    /// conditional on the ETag and Last-Modified of the cached list, so an unchanged
+    /// listing is not downloaded again. Mirrors are tried in order until one answers.
+    pub async fn refresh_keys_list(&self) -> Result<HashMap<String, String>> {
+        let keys_cache_path = self.keys_cache_path();
+        let cached = self.cached_keys();
+        let meta = cached.as_ref().and_then(|_| self.load_cache_meta());
+        // Caches written before mirrors were supported came from the main URL
+        let cached_validators = meta.as_ref().map(|meta| {
+            let source = meta.source.as_deref().unwrap_or(&self.config.ps3_keys_url);
+            (source, &meta.validators)
+        });
+
+        let mirrors = Mirrors::keys(&self.config).with_reporter(self.reporter.clone());
+        if mirrors.is_empty() {
+            return Err(mirrors.no_source_error());
+        }
+        self.reporter.message("Fetching PS3 keys list from Redump...");
+        let (source, fetched) = mirrors.fetch_listing(cached_validators).await?;
+        let (keys, validators) = match fetched {
+            Fetched::NotModified => {
+                // Validators are only sent along with a readable cache
+                let keys = cached.unwrap_or_default();
+                if self.save_cache {
+                    let validators = meta.map(|meta| meta.validators).unwrap_or_default();
+                    self.save_cache_meta(&KeysCacheMeta {
+                        checked_at: unix_now(),
+                        source: Some(source),
+                        validators,
+                    })?;
+                }
+                self.reporter.message(format!("PS3 keys list unchanged, {} keys in cache", keys.len()));
+                return Ok(keys);
+            }
+            Fetched::Changed { body, validators } => (parse_keys_list(&body), validators),
+        };
         
+        if !self.save_cache {
+            self.reporter.message(format!("Fetched {} PS3 keys (cache not written)", keys.len()));
+            return Ok(keys);
+        }
+
         // Save to cache
         self.save_keys_to_cache(&keys_cache_path, &keys)?;
-        
-        println!("Cached {} PS3 keys", keys.len());
-        Ok(keys)
-    }
+        self.save_cache_meta(&KeysCacheMeta {
+            checked_at: unix_now(),
+            source: Some(source),
+            validators,
+        })?;
 
-    /// Fetches the PS3 keys list from the Redump website.
-    async fn fetch_keys_from_web(&self) -> Result<HashMap<String, String>> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
+        self.reporter.message(format!("Cached {} PS3 keys", keys.len()));
 
-        let response = client.get(&self.config.ps3_keys_url).send().await?;
-        
-        if !response.status().is_success() {
-            anyhow::bail!("Failed to fetch PS3 keys list: HTTP {}", response.status());
+        // Key availability is also joined into the catalogue database, where the watchlist
+        // is checked against the games that just gained a key
+        let alerts = Catalog::open(&self.config).and_then(|mut catalog| {
+            let added: HashSet<String> = catalog.store_key_files(&keys)?.into_iter().collect();
+            let keyed_games: Vec<Game> = catalog
+                .load_games()?
+                .into_iter()
+                .filter(|game| added.contains(&game.get_game_id()))
+                .collect();
+            check_watchlist(&self.config, &catalog, &keyed_games, AlertReason::KeyAdded)
+        });
+        match alerts {
+            Ok(alerts) => report_alerts(&self.reporter, &alerts),
+            Err(e) => tracing::warn!("Failed to store keys in the catalogue database or check the watchlist: {:#}", e),
         }
+        Ok(keys)
+    }
 
-        let html_content = response.text().await?;
-        let document = scraper::Html::parse_document(&html_content);
-        let mut keys = HashMap::new();
+    /// Returns whether the keys cache lists a key for the game, without touching the network.
+    /// Returns `None` when there is no readable cache yet.
+    pub fn has_cached_key(&self, game: &Game) -> Option<bool> {
+        let keys = self.cached_keys()?;
+        Some(keys.contains_key(&game.get_game_id()))
+    }
 
-        // Parse the HTML to extract key files using the same approach as scraper
-        let row_selector = scraper::Selector::parse("tbody tr").unwrap();
-        let link_selector = scraper::Selector::parse("td.link a").unwrap();
+    /// Returns the cached keys list (game ID to key file), without touching the network.
+    /// Returns `None` when there is no readable cache yet.
+    pub fn cached_keys(&self) -> Option<HashMap<String, String>> {
+        self.load_keys_from_cache(&self.keys_cache_path()).ok()
+    }
 
-        for row in document.select(&row_selector) {
-            // Skip the parent directory row
-            if let Some(link_element) = row.select(&link_selector).next() {
-                if let Some(href) = link_element.value().attr("href") {
-                    let title = link_element.text().collect::<String>().trim().to_string();
-                    
-                    // Skip if title is empty or doesn't end with .zip
-                    if title.is_empty() || !title.ends_with(".zip") {
-                        continue;
-                    }
+    /// Path of the keys list cache.
+    fn keys_cache_path(&self) -> PathBuf {
+        self.config.keys_folder_path().join("keys_cache.json")
+    }
 
-                    // Extract game ID from filename
-                    let game_id = title.replace(".zip", "");
-                    
-                    // URL-decode the href
-                    let decoded_href = match percent_encoding::percent_decode_str(href).decode_utf8() {
-                        Ok(decoded) => decoded.to_string(),
-                        Err(_) => href.to_string(), // Fallback to original if decoding fails
-                    };
-                    
-                    keys.insert(game_id.clone(), decoded_href.clone());
-                    
-                    // Debug: Print first few keys to see the format
-                    if keys.len() <= 5 {
-                        println!("DEBUG: Parsed key - ID: '{}', href: '{}'", game_id, decoded_href);
-                    }
-                }
-            }
-        }
+    /// Path of the keys list cache metadata.
+    fn keys_cache_meta_path(&self) -> PathBuf {
+        self.config.keys_folder_path().join("keys_cache.meta.json")
+    }
 
-        Ok(keys)
+    /// Reads the keys cache metadata; `None` if there is none yet or it cannot be read.
+    fn load_cache_meta(&self) -> Option<KeysCacheMeta> {
+        let content = fs::read_to_string(self.keys_cache_meta_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Writes the keys cache metadata.
+    fn save_cache_meta(&self, meta: &KeysCacheMeta) -> Result<()> {
+        let json_content = serde_json::to_string_pretty(meta)?;
+        write_atomic(&self.keys_cache_meta_path(), json_content.as_bytes())
     }
 
     /// Downloads a specific key file for a game.
     pub async fn download_key_for_game(&self, game: &Game) -> Result<Option<String>> {
         let game_id = game.get_game_id();
-        println!("DEBUG: Looking for game ID: '{}'", game_id);
+        tracing::debug!("Looking for game ID: '{}'", game_id);
         
         let keys = self.download_keys_list().await?;
-        println!("DEBUG: Found {} keys in cache", keys.len());
+        tracing::debug!("Found {} keys in cache", keys.len());
         
         // Look for the key file for this game
         if let Some(key_file) = keys.get(&game_id) {
-            println!("DEBUG: Found key file: '{}'", key_file);
-            let key_url = format!("{}{}", self.config.ps3_keys_url, key_file);
-            let key_content = self.download_key_file(&key_url).await?;
+            tracing::debug!("Found key file: '{}'", key_file);
+            let key_content = self.download_key_file(key_file).await?;
             
             // Parse the key from the zip file content
             if let Some(key) = self.parse_key_from_zip_content(&key_content) {
-                println!("DEBUG: Successfully extracted key: {}", key);
+                tracing::debug!("Successfully extracted key: {}", key);
                 return Ok(Some(key));
             } else {
-                println!("DEBUG: Failed to parse key from zip content");
+                tracing::debug!("Failed to parse key from zip content");
             }
         } else {
-            println!("DEBUG: No key file found for game ID: '{}'", game_id);
+            tracing::debug!("No key file found for game ID: '{}'", game_id);
             // Let's check what keys we have that might match
             let matching_keys: Vec<_> = keys.keys()
                 .filter(|k| k.to_lowercase().contains(&game_id.to_lowercase()))
                 .take(5)
                 .collect();
             if !matching_keys.is_empty() {
-                println!("DEBUG: Similar keys found: {:?}", matching_keys);
+                tracing::debug!("Similar keys found: {:?}", matching_keys);
             }
         }
         
         Ok(None)
     }
 
-    /// Downloads a key file from the given URL.
-    async fn download_key_file(&self, url: &str) -> Result<Vec<u8>> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
+    /// Downloads a key file, trying the keys mirrors in order. Downloaded key files are kept
+    /// in the keys folder and read from there next time, so they are also available offline.
+    async fn download_key_file(&self, key_file: &str) -> Result<Vec<u8>> {
+        // The keys list holds decoded file names; one with any other path component would
+        // be read from or cached outside the keys folder
+        if Path::new(key_file).file_name().and_then(|name| name.to_str()) != Some(key_file) {
+            anyhow::bail!("Invalid key file name in the keys list: {}", key_file);
+        }
+        let link = file_link(key_file);
 
-        let response = client.get(url).send().await?;
-        
-        if !response.status().is_success() {
-            anyhow::bail!("Failed to download key file: HTTP {}", response.status());
+        let mirrors = Mirrors::keys(&self.config).with_reporter(self.reporter.clone());
+        // Key files on a local mirror are read from there
+        if mirrors.local_file(&link).is_some() {
+            return mirrors.read_file(&link).await;
+        }
+        let cached_path = self.config.keys_folder_path().join(key_file);
+        if let Ok(content) = fs::read(&cached_path) {
+            return Ok(content);
         }
 
-        let content = response.bytes().await?;
-        Ok(content.to_vec())
+        let content = mirrors.read_file(&link).await?;
+        if self.save_cache {
+            if let Err(e) = write_atomic(&cached_path, &content) {
+                tracing::warn!("Failed to cache key file {}: {:#}", cached_path.display(), e);
+            }
+        }
+        Ok(content)
     }
 
     /// Parses the key from the zip file content.
@@ -152,17 +262,17 @@ impl KeyManager {
         // Use zip crate to extract the key from the zip file
         use std::io::Cursor;
         
-        println!("DEBUG: Attempting to parse zip file of {} bytes", zip_data.len());
+        tracing::debug!("Attempting to parse zip file of {} bytes", zip_data.len());
         
         let cursor = Cursor::new(zip_data);
         if let Ok(mut archive) = zip::ZipArchive::new(cursor) {
-            println!("DEBUG: Zip archive opened successfully, {} files found", archive.len());
+            tracing::debug!("Zip archive opened successfully, {} files found", archive.len());
             
             // Look for .key files inside the zip
             for i in 0..archive.len() {
                 if let Ok(mut file) = archive.by_index(i) {
                     let file_name = file.name().to_string();
-                    println!("DEBUG: Found file in zip: '{}'", file_name);
+                    tracing::debug!("Found file in zip: '{}'", file_name);
                     
                     if file_name.ends_with(".key") {
                         let mut buffer = Vec::new();
@@ -171,27 +281,27 @@ impl KeyManager {
                             if let Ok(text) = std::str::from_utf8(&buffer) {
                                 let trimmed = text.trim();
                                 if trimmed.len() == 32 && trimmed.chars().all(|c| c.is_ascii_hexdigit()) {
-                                    println!("DEBUG: Found 32-char hex key in text: {}", trimmed);
+                                    tracing::debug!("Found 32-char hex key in text: {}", trimmed);
                                     return Some(trimmed.to_lowercase());
                                 }
                             }
                             // Try as 16-byte binary
                             if buffer.len() == 16 {
                                 let hex_string = buffer.iter().map(|b| format!("{:02x}", b)).collect::<String>();
-                                println!("DEBUG: Converted 16-byte binary key to hex: {}", hex_string);
+                                tracing::debug!("Converted 16-byte binary key to hex: {}", hex_string);
                                 return Some(hex_string);
                             }
-                            println!("DEBUG: .key file is neither valid text nor 16-byte binary");
+                            tracing::debug!(".key file is neither valid text nor 16-byte binary");
                         } else {
-                            println!("DEBUG: Failed to read .key file as bytes: '{}'", file_name);
+                            tracing::debug!("Failed to read .key file as bytes: '{}'", file_name);
                         }
                     }
                 } else {
-                    println!("DEBUG: Failed to access file at index {}", i);
+                    tracing::debug!("Failed to access file at index {}", i);
                 }
             }
         } else {
-            println!("DEBUG: Failed to open zip archive");
+            tracing::debug!("Failed to open zip archive");
         }
         None
     }
@@ -207,12 +317,11 @@ impl KeyManager {
     fn save_keys_to_cache(&self, cache_path: &Path, keys: &HashMap<String, String>) -> Result<()> {
         // Ensure the directory exists
         if let Some(parent) = cache_path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent).map_err(|e| Error::filesystem("create", parent, e))?;
         }
         
         let json_content = serde_json::to_string_pretty(keys)?;
-        fs::write(cache_path, json_content)?;
-        Ok(())
+        write_atomic(cache_path, json_content.as_bytes())
     }
 
     /// Finds the best matching key for a game.
@@ -224,13 +333,22 @@ impl KeyManager {
             self.find_key_by_alternative_names(game).await,
         ];
 
+        // A failed lookup (e.g. network down) is reported, so it is not mistaken for a missing key
+        let mut first_error = None;
         for strategy in strategies {
-            if let Ok(Some(key)) = strategy {
-                return Ok(Some(key));
+            match strategy {
+                Ok(Some(key)) => return Ok(Some(key)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
             }
         }
 
-        Ok(None)
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
     }
 
     /// Finds key by exact game ID match.
@@ -251,4 +369,31 @@ impl KeyManager {
         // This would try common alternative names for the game
         Ok(None)
     }
-} 
\ No newline at end of file
+}
+
+/// Parses the PS3 keys list (game ID to key file) from a directory listing of the Redump
+/// keys, in any of the layouts `parse_listing` detects.
+fn parse_keys_list(html_content: &str) -> HashMap<String, String> {
+    let mut keys = HashMap::new();
+
+    for entry in parse_listing(html_content) {
+        // Skip anything that is not a key archive
+        let Some(game_id) = entry.name.strip_suffix(".zip") else {
+            continue;
+        };
+
+        // URL-decode the href
+        let decoded_href = match percent_encoding::percent_decode_str(&entry.href).decode_utf8() {
+            Ok(decoded) => decoded.to_string(),
+            Err(_) => entry.href.clone(), // Fallback to original if decoding fails
+        };
+
+        // Debug: Print first few keys to see the format
+        if keys.len() < 5 {
+            tracing::debug!("Parsed key - ID: '{}', href: '{}'", game_id, decoded_href);
+        }
+        keys.insert(game_id.to_string(), decoded_href);
+    }
+
+    keys
+}
@@ -1,7 +1,28 @@
+pub mod batch;
+pub mod catalog;
+pub mod cli;
+pub mod commands;
 pub mod config;
+pub mod dat;
+pub mod details;
+pub mod display;
 pub mod downloader;
+pub mod error;
+pub mod interactive;
+pub mod listing;
 pub mod models;
+pub mod naming;
+pub mod one_game_one_rom;
+pub mod progress;
+pub mod prompt;
+pub mod query;
+pub mod queue;
 pub mod scraper;
+pub mod search;
+pub mod selection;
+pub mod source;
+pub mod tui;
 pub mod utils;
+pub mod watchlist;
 pub mod decryptor;
-pub mod key_manager; 
\ No newline at end of file
+pub mod key_manager; 
@@ -0,0 +1,413 @@
+use crate::{
+    error::Error,
+    utils::{format_size, format_timestamp, parse_listing_date, parse_size},
+};
+use anyhow::Result;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+
+/// HTTP cache validators of a fetched listing, sent back on the next refresh so an
+/// unchanged listing is answered with a bodyless 304.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    /// `ETag` response header
+    pub etag: Option<String>,
+    /// `Last-Modified` response header
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Reads the validators from response headers.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+/// Result of a conditional listing request.
+pub enum Fetched {
+    /// The server confirmed the listing did not change since the validators were issued
+    NotModified,
+    /// The listing HTML with its new validators
+    Changed { body: String, validators: Validators },
+}
+
+/// Fetches a directory listing, conditionally if validators of an earlier fetch are given.
+pub async fn fetch_listing(url: &str, validators: Option<&Validators>) -> Result<Fetched> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let mut request = client.get(url);
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await.map_err(|e| Error::Http {
+        url: url.to_string(),
+        reason: e.to_string(),
+    })?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(Error::Http {
+            url: url.to_string(),
+            reason: format!("HTTP {}", response.status()),
+        }
+        .into());
+    }
+
+    let validators = Validators::from_headers(response.headers());
+    let body = response.text().await?;
+    Ok(Fetched::Changed { body, validators })
+}
+
+/// Characters left as they are when a file name is turned into a link.
+const LINK_SAFE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Turns a file name into a link relative to its listing, percent-encoding it.
+pub fn file_link(name: &str) -> String {
+    utf8_percent_encode(name, LINK_SAFE).to_string()
+}
+
+/// Layout of a directory listing, as served by different web servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingFormat {
+    /// Myrient's table, with `td.link`, `td.size` and `td.date` cells
+    Myrient,
+    /// Apache `mod_autoindex`, as a table or preformatted text
+    Apache,
+    /// nginx `autoindex` HTML, preformatted text
+    Nginx,
+    /// nginx `autoindex_format json`
+    NginxJson,
+    /// lighttpd `mod_dirlisting`
+    Lighttpd,
+    /// Caddy `file_server browse`
+    Caddy,
+    /// Metadata JSON of an Internet Archive item (`archive.org/metadata/<identifier>`)
+    ArchiveOrg,
+    /// Anything else: every link is taken as a file
+    Unknown,
+}
+
+/// A file of a directory listing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListingEntry {
+    /// File name, e.g. "Demon's Souls (Japan).zip"
+    pub name: String,
+    /// Link to the file, relative to the listing URL
+    pub href: String,
+    /// Size as listed, e.g. "23.4 GiB"; empty if not listed
+    pub size: String,
+    /// Exact size in bytes, if the listing gives one
+    pub size_bytes: Option<u64>,
+    /// Modification date as listed; empty if not listed
+    pub date: String,
+    /// Lowercase hex hashes of the file, if the listing gives them
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+/// Detects the layout of a directory listing from its markup.
+pub fn detect_format(body: &str) -> ListingFormat {
+    let trimmed = body.trim_start();
+    if trimmed.starts_with('[') {
+        return ListingFormat::NginxJson;
+    }
+    if trimmed.starts_with('{') {
+        return ListingFormat::ArchiveOrg;
+    }
+    let document = Html::parse_document(body);
+    let has = |selector: &str| document.select(&Selector::parse(selector).unwrap()).next().is_some();
+    if has("td.link a") {
+        ListingFormat::Myrient
+    } else if has("td.n a") {
+        ListingFormat::Lighttpd
+    } else if has("span.name") || body.contains("caddyserver.com") {
+        ListingFormat::Caddy
+    } else if body.contains("?C=N;O=D") || (has("address") && body.contains("Apache")) {
+        ListingFormat::Apache
+    } else if has("pre a") {
+        ListingFormat::Nginx
+    } else {
+        ListingFormat::Unknown
+    }
+}
+
+/// Parses the files of a directory listing, whichever server produced it.
+/// Directories and the parent link are left out.
+pub fn parse_listing(body: &str) -> Vec<ListingEntry> {
+    let format = detect_format(body);
+    tracing::debug!("Parsing {:?} directory listing", format);
+    match format {
+        ListingFormat::Myrient => parse_myrient(body),
+        ListingFormat::NginxJson => parse_nginx_json(body),
+        ListingFormat::ArchiveOrg => parse_archive_metadata(body),
+        ListingFormat::Apache | ListingFormat::Lighttpd | ListingFormat::Caddy => {
+            let entries = parse_table(body);
+            // Apache lists preformatted text unless HTMLTable is set
+            if entries.is_empty() {
+                parse_preformatted(body)
+            } else {
+                entries
+            }
+        }
+        ListingFormat::Nginx => parse_preformatted(body),
+        ListingFormat::Unknown => parse_links(body),
+    }
+}
+
+/// Parses Myrient's table: the name is the link text, next to `td.size` and `td.date` cells.
+fn parse_myrient(body: &str) -> Vec<ListingEntry> {
+    let document = Html::parse_document(body);
+    let row_selector = Selector::parse("tbody tr").unwrap();
+    let link_selector = Selector::parse("td.link a").unwrap();
+    let size_selector = Selector::parse("td.size").unwrap();
+    let date_selector = Selector::parse("td.date").unwrap();
+    let cell_text = |row: &ElementRef, selector: &Selector| {
+        row.select(selector)
+            .next()
+            .map(|cell| cell.text().collect::<String>().trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut entries = Vec::new();
+    for row in document.select(&row_selector) {
+        // Skip the parent directory row
+        let Some(link) = row.select(&link_selector).next() else {
+            continue;
+        };
+        let Some(href) = link.value().attr("href").filter(|href| is_file_link(href)) else {
+            continue;
+        };
+        entries.push(ListingEntry {
+            name: link.text().collect::<String>().trim().to_string(),
+            href: href.to_string(),
+            size: cell_text(&row, &size_selector),
+            date: cell_text(&row, &date_selector),
+            ..Default::default()
+        });
+    }
+    entries
+}
+
+/// Parses a listing laid out as a table with one file per row (Apache `HTMLTable`,
+/// lighttpd, Caddy). The size and date are the row's cells that read as such; Caddy's
+/// exact `data-size` and `<time datetime>` are preferred when present.
+fn parse_table(body: &str) -> Vec<ListingEntry> {
+    let document = Html::parse_document(body);
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("td").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let time_selector = Selector::parse("time[datetime]").unwrap();
+
+    let mut entries = Vec::new();
+    for row in document.select(&row_selector) {
+        let cells: Vec<ElementRef> = row.select(&cell_selector).collect();
+        let Some((link_cell, link)) = cells
+            .iter()
+            .find_map(|cell| Some((cell, cell.select(&link_selector).next()?)))
+        else {
+            continue;
+        };
+        let href = link.value().attr("href").unwrap_or_default();
+        if !is_file_link(href) {
+            continue;
+        }
+
+        let mut entry = file_entry(href);
+        for cell in cells.iter().filter(|cell| cell.id() != link_cell.id()) {
+            let text = cell.text().collect::<String>().trim().to_string();
+            if let Some(bytes) = cell.value().attr("data-size").and_then(|size| size.parse().ok()) {
+                entry.size = format_size(bytes);
+                entry.size_bytes = Some(bytes);
+            } else if let Some(time) = cell.select(&time_selector).next() {
+                entry.date = time.value().attr("datetime").unwrap_or_default().to_string();
+            } else if entry.date.is_empty() && parse_listing_date(&text).is_some() {
+                entry.date = text;
+            } else if entry.size.is_empty() && parse_size(&text).is_some() {
+                entry.size = text;
+            }
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Parses a listing of preformatted text lines, each a link followed by the date and size
+/// (nginx `autoindex`, Apache without `HTMLTable`). nginx cuts long link texts, so names
+/// are taken from the links.
+fn parse_preformatted(body: &str) -> Vec<ListingEntry> {
+    let document = Html::parse_document(body);
+    let pre_selector = Selector::parse("pre").unwrap();
+
+    let mut entries = Vec::new();
+    for pre in document.select(&pre_selector) {
+        let mut current: Option<ListingEntry> = None;
+        for node in pre.children() {
+            match node.value() {
+                Node::Element(element) if element.name() == "a" => {
+                    entries.extend(current.take());
+                    current = element
+                        .attr("href")
+                        .filter(|href| is_file_link(href))
+                        .map(file_entry);
+                }
+                Node::Text(text) => {
+                    if let Some(entry) = current.as_mut() {
+                        // "05-Dec-2023 10:42   1234567" or "2023-12-05 10:42  1.2G"
+                        let columns: Vec<&str> = text.split_whitespace().collect();
+                        if let [day, time, size, ..] = columns[..] {
+                            entry.date = format!("{} {}", day, time);
+                            entry.size = size.to_string();
+                            entry.size_bytes = size.parse().ok();
+                            if let Some(bytes) = entry.size_bytes {
+                                entry.size = format_size(bytes);
+                            }
+                        }
+                        if text.contains('\n') {
+                            entries.extend(current.take());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        entries.extend(current);
+    }
+    entries
+}
+
+/// Parses nginx `autoindex_format json`: `[{"name", "type", "mtime", "size"}]`.
+fn parse_nginx_json(body: &str) -> Vec<ListingEntry> {
+    #[derive(Deserialize)]
+    struct JsonEntry {
+        name: String,
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(default)]
+        mtime: String,
+        size: Option<u64>,
+    }
+
+    let items: Vec<JsonEntry> = match serde_json::from_str(body) {
+        Ok(items) => items,
+        Err(e) => {
+            tracing::warn!("Invalid JSON directory listing: {}", e);
+            return Vec::new();
+        }
+    };
+    items
+        .into_iter()
+        .filter(|item| item.kind == "file")
+        .map(|item| ListingEntry {
+            href: file_link(&item.name),
+            name: item.name,
+            size: item.size.map(format_size).unwrap_or_default(),
+            size_bytes: item.size,
+            date: http_date(&item.mtime),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Parses the `files` of an Internet Archive item's metadata JSON. Sizes and modification
+/// times are given as strings of bytes and Unix seconds; files in folders of the item keep
+/// their path in the link.
+fn parse_archive_metadata(body: &str) -> Vec<ListingEntry> {
+    let metadata: serde_json::Value = match serde_json::from_str(body) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            tracing::warn!("Invalid Internet Archive metadata: {}", e);
+            return Vec::new();
+        }
+    };
+    let field = |file: &serde_json::Value, name: &str| -> Option<String> {
+        match &file[name] {
+            serde_json::Value::String(value) => Some(value.clone()),
+            serde_json::Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    };
+
+    let files = metadata["files"].as_array().cloned().unwrap_or_default();
+    files
+        .iter()
+        .filter_map(|file| {
+            let path = field(file, "name")?;
+            let size_bytes = field(file, "size").and_then(|size| size.parse().ok());
+            Some(ListingEntry {
+                name: path.rsplit('/').next().unwrap_or(&path).to_string(),
+                href: path
+                    .split('/')
+                    .map(file_link)
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                size: size_bytes.map(format_size).unwrap_or_default(),
+                size_bytes,
+                date: field(file, "mtime")
+                    .and_then(|mtime| mtime.parse().ok())
+                    .map(format_timestamp)
+                    .unwrap_or_default(),
+                md5: field(file, "md5").map(|hash| hash.to_lowercase()),
+                sha1: field(file, "sha1").map(|hash| hash.to_lowercase()),
+            })
+        })
+        .collect()
+}
+
+/// Takes every file link of an unknown listing, without size or date.
+fn parse_links(body: &str) -> Vec<ListingEntry> {
+    let document = Html::parse_document(body);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    document
+        .select(&link_selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter(|href| is_file_link(href))
+        .map(file_entry)
+        .collect()
+}
+
+/// Returns false for links to directories, the parent, sort orders and other pages.
+fn is_file_link(href: &str) -> bool {
+    let last_segment = href.rsplit('/').next().unwrap_or_default();
+    !(last_segment.is_empty() || matches!(last_segment, "." | "..") || href.starts_with(['?', '#']))
+}
+
+/// Creates an entry from a file link, named after its last path segment.
+fn file_entry(href: &str) -> ListingEntry {
+    let href = href.split(['?', '#']).next().unwrap_or_default();
+    let link = href.rsplit('/').next().unwrap_or(href);
+    ListingEntry {
+        name: percent_decode_str(link).decode_utf8_lossy().into_owned(),
+        href: link.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Turns an HTTP date ("Tue, 05 Dec 2023 10:42:00 GMT") into "05-Dec-2023 10:42:00",
+/// which `parse_listing_date` reads.
+fn http_date(date: &str) -> String {
+    match date.split_whitespace().collect::<Vec<_>>()[..] {
+        [_, day, month, year, time, ..] => format!("{}-{}-{} {}", day, month, year, time),
+        _ => date.to_string(),
+    }
+}
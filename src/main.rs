@@ -1,25 +1,62 @@
 use anyhow::Result;
+use clap::Parser;
 use ps3_redump_downloader::{
-    config::Config, downloader::Downloader, models::Game, scraper::Scraper, utils::setup_folders,
+    cli::Cli, commands::{build_reporter, refresh_keys_if_requested, run_command}, config::Config, display::print_json_error,
+    downloader::Downloader, error::exit_code, interactive::run_main_loop, scraper::Scraper,
+    utils::setup_folders,
 };
-use tokio::io::{self, AsyncBufReadExt, BufReader};
-use std::io::Write;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
 
+    // Initialize logging (on stderr, so stdout stays usable for --json and event streams)
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    match run(cli.clone()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if cli.options.json {
+                print_json_error(&e);
+            }
+            eprintln!("Error: {:?}", e);
+            // Each kind of failure has its own exit code, see error::Error
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     // Load configuration (searches or creates in recommended locations)
-    let (config, config_path) = Config::load_or_create()?;
-    println!("Using config file at: {}", config_path.display());
+    let (mut config, config_path) = Config::load_or_create()?;
+    config.offline = cli.options.offline;
+    if cli.options.machine_stdout() {
+        eprintln!("Using config file at: {}", config_path.display());
+    } else {
+        println!("Using config file at: {}", config_path.display());
+    }
 
     // Setup working folders
     setup_folders(&config)?;
 
+    // Subcommands run once and exit; without one, start the interactive loop
+    if let Some(command) = cli.command {
+        return run_command(command, &config, &cli.options).await;
+    }
+    if cli.options.json {
+        anyhow::bail!("--json needs a subcommand such as `search` or `download`");
+    }
+
     // Initialize scraper and downloader
-    let scraper = Scraper::new(&config);
-    let downloader = Downloader::new(&config);
+    let reporter = build_reporter(&cli.options)?;
+    let scraper = Scraper::new(&config)
+        .with_reporter(reporter.clone())
+        .with_force_refresh(cli.options.refresh);
+    refresh_keys_if_requested(&config, &cli.options, &reporter).await;
+    let downloader = Downloader::new(&config)
+        .with_reporter(reporter)
+        .with_dry_run(cli.options.dry_run);
 
     // Get PS3 game list
     let games = scraper.get_ps3_list().await?;
@@ -29,73 +66,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-/// Main interactive loop for searching and downloading PS3 games.
-/// Uses async-compatible input/output for better performance.
-async fn run_main_loop(
-    downloader: &Downloader,
-    games: Vec<Game>,
-) -> Result<()> {
-    let stdin = io::stdin();
-    let mut reader = BufReader::new(stdin);
-    let mut input = String::new();
-    loop {
-        print!("Find PS3 title to download (leave empty to exit): ");
-        std::io::stdout().flush()?;
-        input.clear();
-        reader.read_line(&mut input).await?;
-        let search_input = input.trim();
-
-        if search_input.is_empty() {
-            println!("Exiting...");
-            break Ok(());
-        }
-
-        let filtered_games = filter_games(&games, search_input);
-
-        if filtered_games.is_empty() {
-            println!("No PS3 games found\n");
-            continue;
-        }
-
-        print_games(&filtered_games);
-
-        print!("Enter PS3 title number [1-{}]: ", filtered_games.len());
-        std::io::stdout().flush()?;
-        input.clear();
-        reader.read_line(&mut input).await?;
-
-        if let Ok(file_number) = input.trim().parse::<usize>() {
-            if file_number > 0 && file_number <= filtered_games.len() {
-                let selected_game = &filtered_games[file_number - 1];
-                downloader.download_ps3_element(selected_game).await?;
-            } else {
-                println!("Number not in valid range (1-{})\n", filtered_games.len());
-                println!("Please try again..."); // Added user prompt for better UX
-                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-            }
-        }
-    }
-}
-
-/// Filters PS3 games by search string using the precomputed lowercased_title for efficiency.
-fn filter_games<'a>(games: &'a [Game], search: &str) -> Vec<&'a Game> {
-    let search_lower = search.to_lowercase();
-    let searches: Vec<&str> = search_lower.split_whitespace().collect();
-
-    games
-        .iter()
-        .filter(|game| {
-            searches.iter().all(|search| game.lowercased_title.contains(search))
-        })
-        .collect()
-}
-
-/// Displays the list of filtered PS3 games with their titles and sizes.
-fn print_games(games: &[&Game]) {
-    for (index, game) in games.iter().enumerate() {
-        let region_info = game.region.as_ref().map(|r| format!(" ({})", r)).unwrap_or_default();
-        println!("{}. {}{} ({})", index + 1, game.title, region_info, game.size);
-    }
-    println!();
-}
@@ -1,4 +1,11 @@
+use crate::{
+    dat::normalize_serial,
+    naming::{self, ReleaseFlag},
+    search::SearchKey,
+    utils::{parse_listing_date, parse_size},
+};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// Represents a PS3 game entry with title, download link, size, and a lowercased title for fast search.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,24 +16,123 @@ pub struct Game {
     pub link: String,
     /// The size of the game as a string (e.g., '4.2 GB')
     pub size: String,
+    /// The size in bytes parsed from `size`, if it could be parsed
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    /// Date the listing shows for the file, as 'YYYY-MM-DD HH:MM', if it could be parsed
+    #[serde(default)]
+    pub date: Option<String>,
     /// Lowercased version of the title for fast case-insensitive search
     #[serde(skip)]
     pub lowercased_title: String,
-    /// Game type (PS3 for this downloader)
+    /// Normalised title for fuzzy search
+    #[serde(skip)]
+    pub search_key: SearchKey,
+    /// Redump platform the game is listed under
     pub game_type: GameType,
-    /// Whether the game needs decryption (true for PS3 games)
+    /// Whether the game needs decryption (only PS3 games do)
     pub needs_decryption: bool,
-    /// Game region (optional)
+    /// Game region (optional); every region of the title, comma separated
     pub region: Option<String>,
+    /// Name without the parenthesised tags, parsed from the title
+    #[serde(default)]
+    pub name: String,
+    /// Every region in the title, e.g. ["Europe", "Australia"]
+    #[serde(default)]
+    pub regions: Vec<String>,
+    /// Language codes in the title, e.g. ["En", "Fr", "De"]
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Revision, e.g. "1" from "(Rev 1)"
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// Version, e.g. "1.01" from "(v1.01)"
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Disc number of a multi-disc game
+    #[serde(default)]
+    pub disc: Option<u32>,
+    /// Demo, beta, proto, kiosk and promo markers
+    #[serde(default)]
+    pub flags: Vec<ReleaseFlag>,
+    /// Parenthesised tags other than regions and languages, as written,
+    /// e.g. ["Disc 2", "Rev 1", "Greatest Hits"]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Serial(s) from an imported Redump DAT, e.g. "BLUS-30001"
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Exact size and hashes of the disc image, from an imported Redump DAT
+    #[serde(default)]
+    pub dump: Option<DumpInfo>,
+    /// Lowercase hex hashes of the listed ZIP, if the listing gives them (Internet Archive items do)
+    #[serde(default)]
+    pub zip_md5: Option<String>,
+    #[serde(default)]
+    pub zip_sha1: Option<String>,
     /// The key file name for this game (optional)
     pub key_file: Option<String>,
     /// The decryption key for this game (optional)
     pub key: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Size and hashes of a disc image, as listed in a Redump DAT.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpInfo {
+    /// Exact ISO size in bytes
+    pub size: u64,
+    /// Lowercase hex hashes
+    pub crc32: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+/// Redump platform of a game. Only PS3 discs are encrypted; the images of the others
+/// are used as extracted from the ZIP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GameType {
     PS3,
+    PS1,
+    PS2,
+    PSP,
+}
+
+impl GameType {
+    /// Every platform, PS3 first.
+    pub const ALL: [GameType; 4] = [GameType::PS3, GameType::PS1, GameType::PS2, GameType::PSP];
+
+    /// Returns the short name, as in the JSON output and the config keys (e.g. "PS2").
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameType::PS3 => "PS3",
+            GameType::PS1 => "PS1",
+            GameType::PS2 => "PS2",
+            GameType::PSP => "PSP",
+        }
+    }
+
+    /// Parses a short name, ignoring case; "psx" is accepted for PS1.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "psx" => Some(GameType::PS1),
+            name => Self::ALL.into_iter().find(|game_type| game_type.name().eq_ignore_ascii_case(name)),
+        }
+    }
+
+    /// Returns Redump's name of the platform, as in the folder names of its sets.
+    pub fn redump_name(&self) -> &'static str {
+        match self {
+            GameType::PS3 => "Sony - PlayStation 3",
+            GameType::PS1 => "Sony - PlayStation",
+            GameType::PS2 => "Sony - PlayStation 2",
+            GameType::PSP => "Sony - PlayStation Portable",
+        }
+    }
+
+    /// Returns true if the platform's images must be decrypted with a disc key.
+    pub fn needs_decryption(&self) -> bool {
+        *self == GameType::PS3
+    }
 }
 
 impl Game {
@@ -35,26 +141,90 @@ impl Game {
         self.title.replace(".zip", "")
     }
 
-    /// Creates a new Game with lowercased_title initialized
+    /// Fills the fields parsed from the Redump title (regions, languages, revision...)
+    pub fn with_parsed_name(mut self) -> Self {
+        let parsed = naming::parse(&self.title);
+        if !parsed.regions.is_empty() {
+            self.region = Some(parsed.regions.join(", "));
+        }
+        self.name = parsed.name;
+        self.regions = parsed.regions;
+        self.languages = parsed.languages;
+        self.revision = parsed.revision;
+        self.version = parsed.version;
+        self.disc = parsed.disc;
+        self.flags = parsed.flags;
+        self.tags = parsed.tags;
+        self
+    }
+
+    /// Fills `size_bytes` from the listed size if it is not known yet (e.g. in an older cache)
+    pub fn with_size_bytes(mut self) -> Self {
+        self.size_bytes = self.size_bytes.or_else(|| parse_size(&self.size));
+        self
+    }
+
+    /// Returns true if one of the game's serials is the given serial or title ID,
+    /// ignoring case and dashes ("blus30001" matches "BLUS-30001")
+    pub fn has_serial(&self, serial: &str) -> bool {
+        let wanted = normalize_serial(serial);
+        self.serial
+            .as_deref()
+            .is_some_and(|serials| serials.split(',').any(|serial| normalize_serial(serial).starts_with(&wanted)))
+    }
+
+    /// Returns true if the title carries the given flag (e.g. Demo)
+    pub fn has_flag(&self, flag: ReleaseFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Creates a new Game with lowercased_title and search_key initialized
     pub fn with_lowercased(mut self) -> Self {
         self.lowercased_title = self.title.to_lowercase();
+        self.search_key = SearchKey::new(&self.clean_title());
         self
     }
 
     /// Creates a new PS3 game
     pub fn new_ps3(title: String, link: String, size: String, region: Option<String>) -> Self {
+        Self::new(GameType::PS3, title, link, size, region)
+    }
+
+    /// Creates a new game of the given platform
+    pub fn new(game_type: GameType, title: String, link: String, size: String, region: Option<String>) -> Self {
         let game = Self {
             title,
             link,
+            size_bytes: parse_size(&size),
             size,
+            date: None,
             lowercased_title: String::new(),
-            game_type: GameType::PS3,
-            needs_decryption: true, // PS3 games always need decryption
+            search_key: SearchKey::default(),
+            game_type,
+            needs_decryption: game_type.needs_decryption(),
             region,
+            name: String::new(),
+            regions: Vec::new(),
+            languages: Vec::new(),
+            revision: None,
+            version: None,
+            disc: None,
+            flags: Vec::new(),
+            tags: Vec::new(),
+            serial: None,
+            dump: None,
+            zip_md5: None,
+            zip_sha1: None,
             key_file: None,
             key: None,
         };
-        game.with_lowercased()
+        game.with_lowercased().with_parsed_name()
+    }
+
+    /// Sets the date the listing shows for the file, normalising it (see `parse_listing_date`)
+    pub fn with_date(mut self, date: &str) -> Self {
+        self.date = parse_listing_date(date);
+        self
     }
 
     /// Sets the key file for this game
@@ -71,19 +241,40 @@ impl Game {
 
     /// Gets the game identifier for key lookup
     pub fn get_game_id(&self) -> String {
-        // Use the clean title as the game ID to match the key lookup format
-        self.clean_title()
+        // Use the clean title as the game ID to match the key lookup format. Redump reuses
+        // titles across platforms, so the others are prefixed with theirs (e.g. "PS2/...")
+        match self.game_type {
+            GameType::PS3 => self.clean_title(),
+            game_type => format!("{}/{}", game_type.name(), self.clean_title()),
+        }
+    }
+
+    /// Returns the folder the game is downloaded into: a folder per title, inside a folder
+    /// per platform for platforms other than PS3
+    pub fn output_folder(&self, tmp_iso_folder: &Path) -> PathBuf {
+        match self.game_type {
+            GameType::PS3 => tmp_iso_folder.join(self.clean_title()),
+            game_type => tmp_iso_folder.join(game_type.name()).join(self.clean_title()),
+        }
     }
 
-    /// Returns the output ISO filename in the format gamename.iso
+    /// Returns the output ISO filename in the format gamename.iso, with the disc, revision,
+    /// version and flags appended when present (e.g. gamename_disc2_rev1.iso)
     pub fn output_iso_filename(&self) -> String {
-        let clean = self.clean_title();
-        // Extract main game name (up to first parenthesis or end)
-        let main_name = clean
-            .split('(')
-            .next()
-            .unwrap_or("")
-            .trim()
+        let mut parts = vec![self.name.clone()];
+        if let Some(disc) = self.disc {
+            parts.push(format!("disc{}", disc));
+        }
+        if let Some(revision) = &self.revision {
+            parts.push(format!("rev{}", revision));
+        }
+        if let Some(version) = &self.version {
+            parts.push(format!("v{}", version));
+        }
+        parts.extend(self.flags.iter().map(|flag| flag.label().to_string()));
+
+        let main_name = parts
+            .join("_")
             .replace([' ', '-', ',', ':', ';', '\'', '"'], "_")
             .replace("__", "_")
             .trim_matches('_')
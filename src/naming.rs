@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+/// Region names used in Redump titles.
+const REGIONS: &[&str] = &[
+    "World", "USA", "Europe", "Japan", "Asia", "Australia", "Korea", "China", "Taiwan", "Hong Kong",
+    "Canada", "Brazil", "Latin America", "Mexico", "Argentina", "UK", "Ireland", "France", "Germany",
+    "Austria", "Switzerland", "Belgium", "Netherlands", "Italy", "Spain", "Portugal", "Greece",
+    "Scandinavia", "Sweden", "Norway", "Denmark", "Finland", "Poland", "Czech", "Hungary", "Croatia",
+    "Russia", "Turkey", "Israel", "India", "South Africa", "New Zealand", "United Arab Emirates",
+];
+
+/// A pre-release or special-purpose edition marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseFlag {
+    Demo,
+    Beta,
+    Proto,
+    Kiosk,
+    Promo,
+}
+
+impl ReleaseFlag {
+    /// Returns the flag as written in Redump titles.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReleaseFlag::Demo => "Demo",
+            ReleaseFlag::Beta => "Beta",
+            ReleaseFlag::Proto => "Proto",
+            ReleaseFlag::Kiosk => "Kiosk",
+            ReleaseFlag::Promo => "Promo",
+        }
+    }
+}
+
+/// The parts of a Redump title, e.g.
+/// "Gran Turismo 5 (Europe, Australia) (En,Fr,De) (Disc 1) (Rev 1) (Demo)".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedumpName {
+    /// The name before the first parenthesis
+    pub name: String,
+    /// Every region, in title order
+    pub regions: Vec<String>,
+    /// Language codes, e.g. "En", "Fr", "De"
+    pub languages: Vec<String>,
+    /// Revision, e.g. "1" from "(Rev 1)"
+    pub revision: Option<String>,
+    /// Version, e.g. "1.01" from "(v1.01)"
+    pub version: Option<String>,
+    /// Disc number of a multi-disc game
+    pub disc: Option<u32>,
+    /// Demo, beta, proto, kiosk and promo markers
+    pub flags: Vec<ReleaseFlag>,
+    /// Every parenthesised tag other than regions and languages, as written,
+    /// e.g. "Disc 1", "Rev 1", "Beta 2" or "Greatest Hits"
+    pub tags: Vec<String>,
+}
+
+/// Parses a Redump title (with or without .zip) into its parts.
+pub fn parse(title: &str) -> RedumpName {
+    let title = title.trim().trim_end_matches(".zip");
+    let (name, tags) = match title.find(" (") {
+        Some(start) => title.split_at(start),
+        None => (title, ""),
+    };
+    let mut parsed = RedumpName {
+        name: name.trim().to_string(),
+        ..Default::default()
+    };
+
+    for tag in tags.split('(').map(|tag| tag.trim().trim_end_matches(')').trim()) {
+        if tag.is_empty() {
+            continue;
+        }
+        let items: Vec<&str> = tag.split(',').map(str::trim).collect();
+
+        if items.iter().all(|item| REGIONS.contains(item)) {
+            parsed.regions.extend(items.iter().map(|item| item.to_string()));
+            continue;
+        }
+        if items.iter().all(|item| is_language(item)) {
+            parsed.languages.extend(items.iter().map(|item| item.to_string()));
+            continue;
+        }
+
+        parsed.tags.push(tag.to_string());
+        if let Some(revision) = tag.strip_prefix("Rev ") {
+            parsed.revision = Some(revision.to_string());
+        } else if let Some(version) = parse_version(tag) {
+            parsed.version = Some(version);
+        } else if let Some(disc) = tag.strip_prefix("Disc ").and_then(|disc| disc.parse().ok()) {
+            parsed.disc = Some(disc);
+        } else {
+            // "Demo", "Beta 2", "Kiosk Demo"... may name several flags
+            for word in tag.split_whitespace() {
+                let flag = match word {
+                    "Demo" | "Taikenban" => ReleaseFlag::Demo,
+                    "Beta" => ReleaseFlag::Beta,
+                    "Proto" => ReleaseFlag::Proto,
+                    "Kiosk" => ReleaseFlag::Kiosk,
+                    "Promo" => ReleaseFlag::Promo,
+                    _ => continue,
+                };
+                if !parsed.flags.contains(&flag) {
+                    parsed.flags.push(flag);
+                }
+            }
+        }
+    }
+
+    parsed
+}
+
+/// Returns the languages a title implies when it lists none, from its regions
+/// (Redump leaves out the language of single-language releases, e.g. English for USA).
+pub fn implied_languages(regions: &[String]) -> Vec<&'static str> {
+    let mut languages = Vec::new();
+    for region in regions {
+        let language = match region.as_str() {
+            "USA" | "UK" | "Australia" | "Canada" | "Ireland" | "New Zealand" | "World" | "Europe" => "En",
+            "Japan" => "Ja",
+            "Korea" => "Ko",
+            "China" | "Taiwan" | "Hong Kong" => "Zh",
+            "France" => "Fr",
+            "Germany" | "Austria" => "De",
+            "Italy" => "It",
+            "Spain" | "Mexico" | "Latin America" | "Argentina" => "Es",
+            "Brazil" | "Portugal" => "Pt",
+            "Netherlands" => "Nl",
+            "Russia" => "Ru",
+            "Poland" => "Pl",
+            _ => continue,
+        };
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    languages
+}
+
+/// Returns true for a Redump language code such as "En", "Pt-BR" or "Zh-Hant".
+fn is_language(item: &str) -> bool {
+    let mut parts = item.split('-');
+    let code = parts.next().unwrap_or_default();
+    let mut chars = code.chars();
+    let valid_code = code.len() == 2
+        && chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase());
+    valid_code && parts.all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Returns the version number of a "v1.01" or "Version 1.01" tag.
+fn parse_version(tag: &str) -> Option<String> {
+    let version = tag
+        .strip_prefix("Version ")
+        .or_else(|| tag.strip_prefix('v'))?;
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then(|| version.to_string())
+}
@@ -0,0 +1,138 @@
+use crate::{
+    config::Config,
+    models::{Game, GameType},
+    naming::implied_languages,
+    search::normalize,
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Which release of a game 1G1R (one game, one ROM) keeps.
+#[derive(Debug, Clone)]
+pub struct Preferences {
+    /// Regions, most wanted first, e.g. Europe, USA, Japan
+    pub regions: Vec<String>,
+    /// Language codes, most wanted first, e.g. En
+    pub languages: Vec<String>,
+    /// Skip games without any of the preferred languages instead of keeping their best release
+    pub require_language: bool,
+}
+
+impl Preferences {
+    /// Reads the `[1g1r]` preferences from the config.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            regions: config.region_priority.clone(),
+            languages: config.language_priority.clone(),
+            require_language: config.require_language,
+        }
+    }
+
+    /// Position of the game's best region in the preference list, or the list length if none is listed.
+    fn region_rank(&self, game: &Game) -> usize {
+        game.regions
+            .iter()
+            .filter_map(|region| self.regions.iter().position(|wanted| wanted.eq_ignore_ascii_case(region)))
+            .min()
+            .unwrap_or(self.regions.len())
+    }
+
+    /// Position of the game's best language in the preference list, or the list length if none is listed.
+    /// Titles without a language tag use the language of their regions.
+    fn language_rank(&self, game: &Game) -> usize {
+        let languages: Vec<&str> = if game.languages.is_empty() {
+            implied_languages(&game.regions)
+        } else {
+            game.languages.iter().map(String::as_str).collect()
+        };
+        languages
+            .iter()
+            .filter_map(|language| {
+                let base = language.split('-').next().unwrap_or_default();
+                self.languages
+                    .iter()
+                    .position(|wanted| wanted.eq_ignore_ascii_case(language) || wanted.eq_ignore_ascii_case(base))
+            })
+            .min()
+            .unwrap_or(self.languages.len())
+    }
+}
+
+/// Releases of one game are grouped by platform, normalised name and disc number.
+type GroupKey = (GameType, Vec<String>, Option<u32>);
+
+/// The release kept for one game.
+#[derive(Debug, Clone, Copy)]
+pub struct Pick<'a> {
+    /// 1-based position in the catalogue
+    pub index: usize,
+    pub game: &'a Game,
+    /// How many releases of the game it was chosen from, itself included
+    pub variants: usize,
+}
+
+/// Groups releases by game and keeps one per group, in the order they were given.
+///
+/// Releases belong to the same game when they are on the same platform, their names match
+/// after normalisation (see `normalize`) and they are the same disc. Demos, betas, prototypes, kiosk and promo
+/// discs are never picked. Within a game, the release with the most preferred region wins,
+/// then the most preferred language, then the highest revision and version.
+pub fn select_one_per_game<'a>(games: &[(usize, &'a Game)], preferences: &Preferences) -> Vec<Pick<'a>> {
+    let mut groups: HashMap<GroupKey, Vec<(usize, &Game)>> = HashMap::new();
+    for &(index, game) in games {
+        if !game.flags.is_empty() {
+            continue;
+        }
+        groups
+            .entry((game.game_type, normalize(&game.name), game.disc))
+            .or_default()
+            .push((index, game));
+    }
+
+    let mut picked: HashMap<usize, usize> = HashMap::new();
+    for releases in groups.values() {
+        let best = releases
+            .iter()
+            .filter(|(_, game)| {
+                !preferences.require_language || preferences.language_rank(game) < preferences.languages.len()
+            })
+            .min_by_key(|(index, game)| {
+                (
+                    preferences.region_rank(game),
+                    preferences.language_rank(game),
+                    Reverse(release_number(game.revision.as_deref())),
+                    Reverse(release_number(game.version.as_deref())),
+                    *index,
+                )
+            });
+        if let Some((index, _)) = best {
+            picked.insert(*index, releases.len());
+        }
+    }
+
+    games
+        .iter()
+        .filter_map(|&(index, game)| {
+            picked.get(&index).map(|&variants| Pick { index, game, variants })
+        })
+        .collect()
+}
+
+/// Turns a revision or version ("1", "A", "1.02") into comparable numbers; a missing one is lowest.
+fn release_number(release: Option<&str>) -> Vec<u32> {
+    let Some(release) = release else {
+        return Vec::new();
+    };
+    release
+        .split('.')
+        .map(|part| match part.parse::<u32>() {
+            Ok(number) => number,
+            // Lettered revisions: "A" is the first
+            Err(_) => part
+                .chars()
+                .next()
+                .filter(|c| c.is_ascii_alphabetic())
+                .map_or(0, |c| c.to_ascii_uppercase() as u32 - 'A' as u32 + 1),
+        })
+        .collect()
+}
@@ -0,0 +1,366 @@
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Minimum time between two progress events for the same bar.
+const EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Step of the download pipeline a progress event belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Download,
+    Extract,
+    Decrypt,
+}
+
+impl Phase {
+    /// Returns a short human readable name of the phase.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Phase::Download => "Downloading",
+            Phase::Extract => "Extracting",
+            Phase::Decrypt => "Decrypting",
+        }
+    }
+}
+
+/// Progress update emitted by the download, extract and decrypt steps.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// A phase started; `total` is in bytes (or files) when known
+    PhaseStarted { phase: Phase, total: Option<u64> },
+    /// Work done so far in the current phase, with the average speed in units per second
+    Progress {
+        phase: Phase,
+        done: u64,
+        total: Option<u64>,
+        speed: f64,
+    },
+    /// A phase ended, successfully or not
+    PhaseFinished { phase: Phase, success: bool },
+    /// A failed attempt that will be retried
+    Retry {
+        phase: Phase,
+        attempt: u32,
+        max_attempts: u32,
+        reason: String,
+    },
+    /// A phase stopped making visible progress
+    Stalled { phase: Phase, message: String },
+    /// Processing a game failed
+    Error { title: String, message: String },
+    /// A queued game started processing (`index` is 1-based)
+    GameStarted { index: usize, total: usize, title: String },
+    /// A queued game finished, with the error message if it failed
+    GameFinished { title: String, error: Option<String> },
+    /// A free-form status line
+    Message { message: String },
+}
+
+/// One NDJSON line of the event stream.
+#[derive(Serialize)]
+struct EventLine<'a> {
+    /// Unix time in seconds
+    ts: f64,
+    #[serde(flatten)]
+    event: &'a ProgressEvent,
+}
+
+/// Shared writer of the NDJSON event stream.
+#[derive(Clone)]
+struct EventStream(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventStream")
+    }
+}
+
+/// Routes pipeline output either to the console (println and indicatif bars)
+/// or, when captured, to a channel of `ProgressEvent`s. Events can additionally
+/// be written as NDJSON to a stream.
+#[derive(Debug, Clone, Default)]
+pub struct Reporter {
+    sender: Option<UnboundedSender<ProgressEvent>>,
+    events: Option<EventStream>,
+    /// Print to stderr instead of stdout, keeping stdout for machine-readable output
+    stderr: bool,
+}
+
+impl Reporter {
+    /// Creates a reporter that prints to the console.
+    pub fn console() -> Self {
+        Self::default()
+    }
+
+    /// Creates a reporter that prints to stderr, leaving stdout to machine-readable output.
+    pub fn stderr() -> Self {
+        Self {
+            stderr: true,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a reporter that sends every status line and progress update to the channel.
+    pub fn channel(sender: UnboundedSender<ProgressEvent>) -> Self {
+        Self {
+            sender: Some(sender),
+            ..Self::default()
+        }
+    }
+
+    /// Also writes every event as one JSON object per line to `target`, which is
+    /// `stdout`, `stderr`, `fd:N` (an already open file descriptor) or a file path.
+    /// When the stream goes to stdout, status lines and bars move to stderr.
+    pub fn with_event_stream(mut self, target: &str) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = match target {
+            "stdout" | "-" => {
+                self.stderr = true;
+                Box::new(std::io::stdout())
+            }
+            "stderr" => Box::new(std::io::stderr()),
+            _ => {
+                let path = match target.strip_prefix("fd:") {
+                    Some(fd) => {
+                        let fd: u32 = fd
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid file descriptor in '{}'", target))?;
+                        format!("/dev/fd/{}", fd)
+                    }
+                    None => target.to_string(),
+                };
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to open event stream {}: {}", path, e))?;
+                Box::new(file)
+            }
+        };
+        self.events = Some(EventStream(Arc::new(Mutex::new(writer))));
+        Ok(self)
+    }
+
+    /// Returns true if output goes to a channel instead of the console.
+    pub fn is_captured(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Returns true if progress events are consumed by a channel or an event stream.
+    fn wants_events(&self) -> bool {
+        self.sender.is_some() || self.events.is_some()
+    }
+
+    /// Prints a status line, or sends it as a message event when captured.
+    pub fn message(&self, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(events) = &self.events {
+            write_event(events, &ProgressEvent::Message {
+                message: message.clone(),
+            });
+        }
+        match &self.sender {
+            Some(sender) => {
+                let _ = sender.send(ProgressEvent::Message { message });
+            }
+            None if self.stderr => eprintln!("{}", message),
+            None => {
+                println!("{}", message);
+                std::io::stdout().flush().ok();
+            }
+        }
+    }
+
+    /// Creates a progress bar for a phase. The bar is hidden when captured.
+    pub fn bar(&self, phase: Phase, total: Option<u64>, template: &str) -> PhaseBar {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total),
+            None => ProgressBar::new_spinner(),
+        };
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        bar.set_draw_target(self.draw_target());
+        if !self.is_captured() {
+            bar.tick();
+            std::io::stdout().flush().ok();
+        }
+
+        self.send(ProgressEvent::PhaseStarted { phase, total });
+
+        PhaseBar {
+            bar,
+            phase,
+            reporter: self.clone(),
+            started: Instant::now(),
+            start_position: Mutex::new(None),
+            last_event: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Creates a steadily ticking spinner for work without measurable progress.
+    pub fn spinner(&self, template: &str) -> ProgressBar {
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(
+            ProgressStyle::default_spinner()
+                .template(template)
+                .unwrap()
+                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
+        );
+        spinner.set_draw_target(self.draw_target());
+        if !self.is_captured() {
+            spinner.enable_steady_tick(Duration::from_millis(120));
+        }
+        spinner
+    }
+
+    /// Sends an event to the channel and the event stream, if any.
+    pub fn send(&self, event: ProgressEvent) {
+        if let Some(events) = &self.events {
+            write_event(events, &event);
+        }
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Bars are drawn only on a terminal, so redirected output stays free of control codes.
+    fn draw_target(&self) -> ProgressDrawTarget {
+        if self.is_captured() {
+            ProgressDrawTarget::hidden()
+        } else if self.stderr {
+            if std::io::stderr().is_terminal() {
+                ProgressDrawTarget::stderr()
+            } else {
+                ProgressDrawTarget::hidden()
+            }
+        } else if std::io::stdout().is_terminal() {
+            ProgressDrawTarget::stdout()
+        } else {
+            ProgressDrawTarget::hidden()
+        }
+    }
+}
+
+fn write_event(events: &EventStream, event: &ProgressEvent) {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    if let Ok(line) = serde_json::to_string(&EventLine { ts, event }) {
+        let mut writer = events.0.lock().unwrap();
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
+
+/// An indicatif bar that also reports its progress as events.
+pub struct PhaseBar {
+    bar: ProgressBar,
+    phase: Phase,
+    reporter: Reporter,
+    started: Instant,
+    /// Position of the first update, so resumed downloads report their real speed
+    start_position: Mutex<Option<u64>>,
+    last_event: Mutex<Instant>,
+}
+
+impl PhaseBar {
+    /// Sets the current position.
+    pub fn set_position(&self, position: u64) {
+        self.bar.set_position(position);
+        self.report(false);
+    }
+
+    /// Advances the position by `delta`.
+    pub fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+        self.report(false);
+    }
+
+    /// Returns the current position.
+    pub fn position(&self) -> u64 {
+        self.bar.position()
+    }
+
+    /// Returns the total length, if known.
+    pub fn length(&self) -> Option<u64> {
+        self.bar.length()
+    }
+
+    /// Prints a line above the bar (or sends it as a message when captured).
+    pub fn println(&self, message: impl Into<String>) {
+        let message = message.into();
+        if self.reporter.is_captured() || self.bar.is_hidden() {
+            self.reporter.message(message);
+        } else {
+            self.bar.println(message);
+        }
+    }
+
+    /// Warns that the phase stopped making progress. The bar stops drawing,
+    /// but the phase continues and still reports its progress.
+    pub fn stalled(&self, message: &str) {
+        self.println(message);
+        self.bar.abandon_with_message("Stalled");
+        self.reporter.send(ProgressEvent::Stalled {
+            phase: self.phase,
+            message: message.to_string(),
+        });
+    }
+
+    /// Finishes the phase successfully.
+    pub fn finish_with_message(&self, message: &'static str) {
+        self.report(true);
+        self.bar.finish_with_message(message);
+        self.reporter.send(ProgressEvent::PhaseFinished {
+            phase: self.phase,
+            success: true,
+        });
+    }
+
+    /// Ends the phase without completing it.
+    pub fn abandon_with_message(&self, message: &'static str) {
+        self.report(true);
+        self.bar.abandon_with_message(message);
+        self.reporter.send(ProgressEvent::PhaseFinished {
+            phase: self.phase,
+            success: false,
+        });
+    }
+
+    fn report(&self, force: bool) {
+        if !self.reporter.wants_events() {
+            return;
+        }
+        let position = self.bar.position();
+        let start_position = *self.start_position.lock().unwrap().get_or_insert(position);
+
+        let mut last_event = self.last_event.lock().unwrap();
+        if force || last_event.elapsed() >= EVENT_INTERVAL {
+            *last_event = Instant::now();
+            let elapsed = self.started.elapsed().as_secs_f64();
+            let speed = if elapsed > 0.0 {
+                position.saturating_sub(start_position) as f64 / elapsed
+            } else {
+                0.0
+            };
+            self.reporter.send(ProgressEvent::Progress {
+                phase: self.phase,
+                done: position,
+                total: self.bar.length(),
+                speed,
+            });
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{config::Config, models::Game};
+use anyhow::Result;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, Helper};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Maximum number of search queries kept in the history file.
+const MAX_HISTORY: usize = 1000;
+
+/// Completes the word under the cursor with words taken from the catalogue titles.
+pub struct TitleCompleter {
+    /// Every distinct title word, in its original case
+    words: BTreeSet<String>,
+    /// Lowercased clean titles, to narrow candidates to titles matching the words already typed
+    titles: Vec<String>,
+}
+
+impl TitleCompleter {
+    /// Builds the word list from the catalogue.
+    pub fn new(games: &[Game]) -> Self {
+        let mut words = BTreeSet::new();
+        for game in games {
+            for word in game.clean_title().split_whitespace() {
+                let word = word.trim_matches(|c: char| matches!(c, '(' | ')' | ',' | '[' | ']'));
+                if word.len() > 1 {
+                    words.insert(word.to_string());
+                }
+            }
+        }
+
+        Self {
+            words,
+            titles: games.iter().map(|game| game.clean_title().to_lowercase()).collect(),
+        }
+    }
+
+    /// Returns the title words starting with `prefix` that appear in a title
+    /// containing all of the `typed` words.
+    fn candidates(&self, typed: &[String], prefix: &str) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        let titles: Vec<&String> = self
+            .titles
+            .iter()
+            .filter(|title| typed.iter().all(|word| title.contains(word.as_str())))
+            .collect();
+
+        let mut seen = BTreeSet::new();
+        self.words
+            .iter()
+            .filter(|word| word.to_lowercase().starts_with(&prefix))
+            .filter(|word| {
+                let lower = word.to_lowercase();
+                titles.iter().any(|title| title.contains(&lower))
+            })
+            // Titles often repeat a word in several cases; offer it once
+            .filter(|word| seen.insert(word.to_lowercase()))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Completer for TitleCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let prefix = &before[start..];
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let typed: Vec<String> = before[..start].split_whitespace().map(str::to_lowercase).collect();
+        let pairs = self
+            .candidates(&typed, prefix)
+            .into_iter()
+            .map(|word| Pair {
+                display: word.clone(),
+                replacement: word,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for TitleCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for TitleCompleter {}
+
+impl Validator for TitleCompleter {}
+
+impl Helper for TitleCompleter {}
+
+/// Line editor for the interactive loop, with search history kept across runs.
+pub struct Prompt {
+    editor: Editor<TitleCompleter, DefaultHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl Prompt {
+    /// Creates the editor, completing titles from `games` and loading the saved search history.
+    pub fn new(games: &[Game]) -> Result<Self> {
+        let editor_config = rustyline::Config::builder()
+            .completion_type(CompletionType::List)
+            .auto_add_history(false)
+            .max_history_size(MAX_HISTORY)?
+            .history_ignore_dups(true)?
+            .history_ignore_space(true)
+            .build();
+        let mut editor = Editor::with_config(editor_config)?;
+        editor.set_helper(Some(TitleCompleter::new(games)));
+
+        let history_path = Config::history_path();
+        if let Some(path) = &history_path {
+            // A missing history file just means nothing was searched yet
+            if path.exists() {
+                if let Err(e) = editor.load_history(path) {
+                    tracing::warn!("Failed to load search history {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        Ok(Self { editor, history_path })
+    }
+
+    /// Reads a line with editing and completion. Returns `None` on end of input or Ctrl-C.
+    pub fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+        match tokio::task::block_in_place(|| self.editor.readline(prompt)) {
+            Ok(line) => Ok(Some(line)),
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds a search query to the history and saves it, so it survives a crash or Ctrl-C.
+    pub fn remember(&mut self, query: &str) {
+        if self.editor.add_history_entry(query).unwrap_or(false) {
+            self.save_history();
+        }
+    }
+
+    fn save_history(&mut self) {
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = self.editor.save_history(path) {
+            tracing::warn!("Failed to save search history {}: {}", path.display(), e);
+        }
+    }
+}
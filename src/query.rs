@@ -0,0 +1,356 @@
+use crate::{
+    config::Config,
+    details::find_downloaded_iso,
+    key_manager::KeyManager,
+    models::{Game, GameType},
+    naming::{implied_languages, ReleaseFlag},
+    search::{normalize, search_indexed, SearchMatch},
+    utils::parse_size,
+};
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Filter names accepted before a ':' (or a comparison for `size`), for error messages.
+const FILTER_NAMES: &str = "platform:, region:, lang:, size<, size>, rev:, disc:, is:, has:, owned:, sort:";
+
+/// Every name `split_filter` reads as a filter; other `word:rest` terms are search words.
+const KNOWN_FILTERS: [&str; 12] = [
+    "platform", "region", "lang", "language", "size", "rev", "revision", "disc", "is", "has", "owned", "sort",
+];
+
+/// A parsed catalogue query: free search text plus filters and an optional sort order, e.g.
+/// `final fantasy platform:ps3 region:usa,europe lang:fr size<8GB -demo rev:1 disc:2 has:key owned:no sort:size`.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Search words, matched as by `search_games`
+    pub text: String,
+    /// Normalised words that must not appear in the title ("-demo")
+    excluded_words: Vec<String>,
+    filters: Vec<Filter>,
+    sort: Option<(SortKey, bool)>,
+}
+
+/// One filter term, possibly negated with a leading '-'.
+#[derive(Debug, Clone)]
+struct Filter {
+    kind: FilterKind,
+    negated: bool,
+}
+
+#[derive(Debug, Clone)]
+enum FilterKind {
+    /// Any of the platforms
+    Platform(Vec<GameType>),
+    /// Any of the regions, matched as case-insensitive prefixes ("eu" for Europe)
+    Region(Vec<String>),
+    /// Any of the language codes, case-insensitive ("pt" also matches "Pt-BR")
+    Language(Vec<String>),
+    Size(Ordering, bool, u64),
+    /// `None` for the original release (`rev:0`)
+    Revision(Option<String>),
+    Disc(u32),
+    Flag(ReleaseFlag),
+    HasKey,
+    Owned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Score,
+    Title,
+    Region,
+    Size,
+    Index,
+}
+
+/// Local state some filters need: the keys cache for `has:key` and the download folders
+/// for `owned:`. The keys cache is only read when the query uses it.
+pub struct QueryContext<'a> {
+    config: &'a Config,
+    keys: Option<HashMap<String, String>>,
+}
+
+impl<'a> QueryContext<'a> {
+    /// Loads what the query needs, without any network access.
+    pub fn new(config: &'a Config, query: &Query) -> Self {
+        let needs_keys = query
+            .filters
+            .iter()
+            .any(|filter| matches!(filter.kind, FilterKind::HasKey));
+        Self {
+            config,
+            keys: if needs_keys {
+                KeyManager::new(config).cached_keys()
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl Query {
+    /// Parses a query. Words are search text unless they look like `name:value`
+    /// or `size<value`; a leading '-' negates a filter or excludes a title word, and
+    /// double quotes keep spaces inside a word (`region:"hong kong"`).
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut query = Query::default();
+        let mut text = Vec::new();
+
+        for token in tokenize(input) {
+            let (negated, term) = match token.strip_prefix('-') {
+                Some(term) if !term.is_empty() => (true, term),
+                _ => (false, token.as_str()),
+            };
+
+            let Some((name, operator, value)) = split_filter(term) else {
+                if negated {
+                    query.excluded_words.extend(normalize(term));
+                } else {
+                    text.push(term.to_string());
+                }
+                continue;
+            };
+
+            let kind = match (name.as_str(), operator) {
+                ("platform", ":") => FilterKind::Platform(
+                    split_list(value)
+                        .iter()
+                        .map(|name| {
+                            GameType::from_name(name).ok_or_else(|| {
+                                anyhow::anyhow!("Unknown platform '{}' in '{}', use ps3, ps1, ps2 or psp", name, term)
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                ("region", ":") => FilterKind::Region(split_list(value)),
+                ("lang" | "language", ":") => FilterKind::Language(split_list(value)),
+                ("size", ":") => anyhow::bail!("Use a comparison with size, e.g. size<8GB or size>=500MB"),
+                ("size", operator) => {
+                    let bytes = parse_size(value)
+                        .ok_or_else(|| anyhow::anyhow!("Invalid size '{}' in '{}', e.g. 8GB or 700MB", value, term))?;
+                    let (ordering, or_equal) = match operator {
+                        "<" => (Ordering::Less, false),
+                        "<=" => (Ordering::Less, true),
+                        ">" => (Ordering::Greater, false),
+                        ">=" => (Ordering::Greater, true),
+                        _ => (Ordering::Equal, true),
+                    };
+                    FilterKind::Size(ordering, or_equal, bytes)
+                }
+                ("rev" | "revision", ":") => match value {
+                    "0" | "none" => FilterKind::Revision(None),
+                    _ => FilterKind::Revision(Some(value.to_string())),
+                },
+                ("disc", ":") => FilterKind::Disc(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid disc number '{}' in '{}'", value, term))?,
+                ),
+                ("is", ":") => FilterKind::Flag(match value.to_lowercase().as_str() {
+                    "demo" => ReleaseFlag::Demo,
+                    "beta" => ReleaseFlag::Beta,
+                    "proto" => ReleaseFlag::Proto,
+                    "kiosk" => ReleaseFlag::Kiosk,
+                    "promo" => ReleaseFlag::Promo,
+                    _ => anyhow::bail!("Unknown '{}', use is:demo, is:beta, is:proto, is:kiosk or is:promo", term),
+                }),
+                ("has", ":") => match value.to_lowercase().as_str() {
+                    "key" => FilterKind::HasKey,
+                    _ => anyhow::bail!("Unknown '{}', use has:key", term),
+                },
+                ("owned", ":") => {
+                    let owned = match value.to_lowercase().as_str() {
+                        "yes" | "true" => true,
+                        "no" | "false" => false,
+                        _ => anyhow::bail!("Unknown '{}', use owned:yes or owned:no", term),
+                    };
+                    query.filters.push(Filter {
+                        kind: FilterKind::Owned,
+                        negated: negated == owned,
+                    });
+                    continue;
+                }
+                ("sort", ":") => {
+                    let (descending, key) = match value.strip_prefix('-') {
+                        Some(key) => (true, key),
+                        None => (negated, value),
+                    };
+                    let key = match key.to_lowercase().as_str() {
+                        "score" | "relevance" => SortKey::Score,
+                        "title" | "name" => SortKey::Title,
+                        "region" => SortKey::Region,
+                        "size" => SortKey::Size,
+                        "index" => SortKey::Index,
+                        _ => anyhow::bail!("Unknown '{}', use sort:score, title, region, size or index", term),
+                    };
+                    query.sort = Some((key, descending));
+                    continue;
+                }
+                _ => anyhow::bail!("Unknown filter '{}'. Known filters: {}", term, FILTER_NAMES),
+            };
+            query.filters.push(Filter { kind, negated });
+        }
+
+        query.text = text.join(" ");
+        Ok(query)
+    }
+
+    /// Returns true if the game passes every filter and contains no excluded word.
+    /// The search text is not checked here.
+    pub fn accepts(&self, game: &Game, context: &QueryContext) -> bool {
+        if self
+            .excluded_words
+            .iter()
+            .any(|word| game.search_key.words.contains(word))
+        {
+            return false;
+        }
+        self.filters
+            .iter()
+            .all(|filter| filter.kind.matches(game, context) != filter.negated)
+    }
+}
+
+impl FilterKind {
+    fn matches(&self, game: &Game, context: &QueryContext) -> bool {
+        match self {
+            FilterKind::Platform(wanted) => wanted.contains(&game.game_type),
+            FilterKind::Region(wanted) => game.regions.iter().any(|region| {
+                let region = region.to_lowercase();
+                wanted.iter().any(|prefix| region.starts_with(prefix.as_str()))
+            }),
+            FilterKind::Language(wanted) => {
+                let languages: Vec<String> = if game.languages.is_empty() {
+                    implied_languages(&game.regions).iter().map(|l| l.to_lowercase()).collect()
+                } else {
+                    game.languages.iter().map(|l| l.to_lowercase()).collect()
+                };
+                languages.iter().any(|language| {
+                    let base = language.split('-').next().unwrap_or_default();
+                    wanted.iter().any(|code| code == language || code == base)
+                })
+            }
+            FilterKind::Size(ordering, or_equal, bytes) => game
+                .size_bytes
+                .is_some_and(|size| size.cmp(bytes) == *ordering || (*or_equal && size == *bytes)),
+            FilterKind::Revision(wanted) => match (wanted, &game.revision) {
+                (None, revision) => revision.is_none(),
+                (Some(wanted), Some(revision)) => wanted.eq_ignore_ascii_case(revision),
+                (Some(_), None) => false,
+            },
+            // A single-disc game is disc 1
+            FilterKind::Disc(disc) => game.disc.unwrap_or(1) == *disc,
+            FilterKind::Flag(flag) => game.has_flag(*flag),
+            FilterKind::HasKey => context
+                .keys
+                .as_ref()
+                .is_some_and(|keys| keys.contains_key(&game.get_game_id())),
+            FilterKind::Owned => find_downloaded_iso(context.config, game).is_some(),
+        }
+    }
+}
+
+/// Parses and runs a query against the catalogue (see `run_query`).
+pub fn query_games<'a>(config: &Config, games: &'a [Game], input: &str) -> Result<Vec<SearchMatch<'a>>> {
+    let query = Query::parse(input)?;
+    let indexed: Vec<(usize, &Game)> = games.iter().enumerate().map(|(i, game)| (i + 1, game)).collect();
+    Ok(query_candidates(config, &indexed, &query))
+}
+
+/// Runs a parsed query against candidate games that carry their 1-based catalogue index,
+/// such as the titles the catalogue's full-text index finds for the search text.
+pub fn query_candidates<'a>(config: &Config, games: &[(usize, &'a Game)], query: &Query) -> Vec<SearchMatch<'a>> {
+    let context = QueryContext::new(config, query);
+    run_query_indexed(games, query, &context)
+}
+
+/// Returns the games matching the query's search text and filters. Results are ordered
+/// by relevance when there is search text and in catalogue order otherwise, unless the
+/// query has a `sort:` term.
+pub fn run_query<'a>(games: &'a [Game], query: &Query, context: &QueryContext) -> Vec<SearchMatch<'a>> {
+    let indexed: Vec<(usize, &Game)> = games.iter().enumerate().map(|(i, game)| (i + 1, game)).collect();
+    run_query_indexed(&indexed, query, context)
+}
+
+/// Same as `run_query`, over games that already carry their 1-based catalogue index.
+fn run_query_indexed<'a>(
+    games: &[(usize, &'a Game)],
+    query: &Query,
+    context: &QueryContext,
+) -> Vec<SearchMatch<'a>> {
+    let mut matches: Vec<SearchMatch> = search_indexed(games, &query.text)
+        .into_iter()
+        .filter(|hit| query.accepts(hit.game, context))
+        .collect();
+
+    if let Some((key, descending)) = query.sort {
+        // Stable sort, so ties keep relevance or catalogue order
+        matches.sort_by(|a, b| {
+            let ordering = match key {
+                SortKey::Score => b.score.total_cmp(&a.score),
+                SortKey::Title => a.game.lowercased_title.cmp(&b.game.lowercased_title),
+                SortKey::Region => a.game.region.cmp(&b.game.region),
+                // Unknown sizes go last
+                SortKey::Size => match (a.game.size_bytes, b.game.size_bytes) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                },
+                SortKey::Index => a.index.cmp(&b.index),
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+    matches
+}
+
+/// Splits a query into words, keeping double-quoted parts together without the quotes.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Splits `name:value`, `size<=value` and the like into lowercase name, operator and value.
+/// Returns `None` for plain words, including titles ending in ':' like "Batman:" and words
+/// whose prefix is no filter name, like "Re:Zero".
+fn split_filter(term: &str) -> Option<(String, &str, &str)> {
+    let position = term.find([':', '<', '>', '='])?;
+    let (name, rest) = term.split_at(position);
+    if !KNOWN_FILTERS.contains(&name.to_lowercase().as_str()) {
+        return None;
+    }
+    let operator = ["<=", ">=", ":", "<", ">", "="]
+        .into_iter()
+        .find(|operator| rest.starts_with(operator))?;
+    let value = &rest[operator.len()..];
+    (!value.is_empty()).then(|| (name.to_lowercase(), operator, value))
+}
+
+/// Splits a comma-separated filter value into lowercase items.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
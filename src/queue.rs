@@ -0,0 +1,109 @@
+use crate::{
+    details::free_space,
+    downloader::{DownloadReport, Downloader},
+    models::Game,
+    progress::ProgressEvent,
+    utils::format_size,
+};
+use serde::Serialize;
+
+/// Outcome of one game processed by `download_queue`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueResult {
+    /// The cleaned title of the game
+    pub title: String,
+    /// The report of a successful download, or the plan of a dry run
+    pub report: Option<DownloadReport>,
+    /// The error message if the game failed
+    pub error: Option<String>,
+}
+
+impl QueueResult {
+    /// Returns true if the game was downloaded and decrypted, or planned without problems.
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Returns true if this was only a dry run.
+    pub fn is_plan(&self) -> bool {
+        self.report.as_ref().is_some_and(|report| report.plan.is_some())
+    }
+}
+
+/// Downloads the given games one after another, continuing past failures.
+pub async fn download_queue(downloader: &Downloader, games: &[&Game]) -> Vec<QueueResult> {
+    let reporter = downloader.reporter();
+    let mut results = Vec::with_capacity(games.len());
+
+    // Warn up front rather than failing halfway through a long queue. Games are processed
+    // one at a time, so only the largest one needs room for a second copy while decrypting.
+    if games.len() > 1 {
+        let total: u64 = games.iter().filter_map(|game| game.size_bytes).sum();
+        let largest = games.iter().filter_map(|game| game.size_bytes).max().unwrap_or(0);
+        let folder = downloader.config().tmp_iso_folder_path();
+        if let Some(free) = free_space(&folder).filter(|free| *free < total + largest) {
+            reporter.message(format!(
+                "Warning: these {} games need about {} but only {} is free in {}",
+                games.len(),
+                format_size(total + largest),
+                format_size(free),
+                folder.display()
+            ));
+        }
+    }
+
+    for (index, game) in games.iter().enumerate() {
+        if games.len() > 1 {
+            reporter.message(format!("\n[{}/{}] {}", index + 1, games.len(), game.clean_title()));
+        }
+        reporter.send(ProgressEvent::GameStarted {
+            index: index + 1,
+            total: games.len(),
+            title: game.clean_title(),
+        });
+
+        let (report, error) = match downloader.download_ps3_element(game).await {
+            Ok(report) => {
+                // A dry run succeeds, but the problems it found would fail the real download
+                let error = report
+                    .plan
+                    .as_ref()
+                    .filter(|plan| !plan.is_ready())
+                    .map(|plan| format!("Dry run found problems: {}", plan.problems.join("; ")));
+                (Some(report), error)
+            }
+            Err(e) => {
+                reporter.message(format!("Failed to process {}: {}", game.clean_title(), e));
+                (None, Some(e.to_string()))
+            }
+        };
+
+        reporter.send(ProgressEvent::GameFinished {
+            title: game.clean_title(),
+            error: error.clone(),
+        });
+
+        results.push(QueueResult {
+            title: game.clean_title(),
+            report,
+            error,
+        });
+    }
+
+    results
+}
+
+/// Prints which queued games succeeded and which failed.
+pub fn print_queue_summary(results: &[QueueResult]) {
+    let succeeded = results.iter().filter(|r| r.succeeded()).count();
+    println!("\nSummary: {} succeeded, {} failed", succeeded, results.len() - succeeded);
+
+    for result in results {
+        match &result.error {
+            None if result.is_plan() => println!("  PLANNED {}", result.title),
+            None => println!("  OK     {}", result.title),
+            Some(error) => println!("  FAILED {} ({})", result.title, error),
+        }
+    }
+    println!();
+}
@@ -1,14 +1,26 @@
-use crate::{config::Config, models::Game};
+use crate::{
+    catalog::{Catalog, ChangeKind, ListingSource},
+    config::Config,
+    error::Error,
+    listing::{parse_listing, Fetched, Validators},
+    models::{Game, GameType},
+    progress::Reporter,
+    source::Mirrors,
+    utils::is_expired,
+    watchlist::{check_watchlist, report_alerts, AlertReason},
+};
 use anyhow::Result;
-use reqwest;
-use scraper::{Html, Selector};
 use serde_json;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
-/// Scraper handles fetching and parsing PS3 game lists from Redump.
+/// Scraper handles fetching and parsing the game lists of the configured platforms from Redump.
 pub struct Scraper {
     config: Config,
+    reporter: Reporter,
+    /// Check the source even if the cached list has not expired
+    force_refresh: bool,
 }
 
 impl Scraper {
@@ -16,103 +28,212 @@ impl Scraper {
     pub fn new(config: &Config) -> Self {
         Self {
             config: config.clone(),
+            reporter: Reporter::console(),
+            force_refresh: false,
         }
     }
 
-    /// Fetches the PS3 game list, either from cache or from the web.
+    /// Routes status lines through the given reporter.
+    pub fn with_reporter(mut self, reporter: Reporter) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Makes `get_ps3_list` check the source now, even if the cached list has not expired.
+    pub fn with_force_refresh(mut self, force_refresh: bool) -> Self {
+        self.force_refresh = force_refresh;
+        self
+    }
+
+    /// Fetches the game list, either from the catalogue database or from the web.
+    /// The cached list is checked for changes once it is older than `CACHE_TTL_HOURS`.
+    /// A JSON cache written by older versions is imported into the database on first use.
     pub async fn get_ps3_list(&self) -> Result<Vec<Game>> {
-        let json_path = self.config.list_ps3_json_path();
+        let mut catalog = Catalog::open(&self.config)?;
 
-        // Try to load from cache first
-        if json_path.exists() {
-            if let Ok(games) = self.load_from_cache(&json_path) {
-                println!("Loaded {} PS3 games from cache", games.len());
+        if catalog.is_empty()? {
+            if let Some(games) = self.migrate_json_cache(&mut catalog)? {
                 return Ok(games);
             }
+            // Fetch from web if there is no cache yet
+            return self.refresh_ps3_list().await;
         }
 
-        // Fetch from web if cache doesn't exist or is invalid
-        println!("Fetching PS3 game list from Redump...");
-        let games = self.fetch_ps3_list_from_web().await?;
-        
-        // Save to cache
-        self.save_to_cache(&json_path, &games)?;
-        
-        println!("Cached {} PS3 games", games.len());
+        if self.refresh_due(&catalog)? {
+            // An outdated list is better than none while the source cannot be reached
+            match self.refresh_ps3_list().await {
+                Ok(games) => return Ok(games),
+                Err(e) => self
+                    .reporter
+                    .message(format!("Could not refresh the game list, using the cached one: {}", e)),
+            }
+        }
+
+        let games = catalog.load_games()?;
+        self.reporter.message(format!("Loaded {} games from cache", games.len()));
         Ok(games)
     }
 
-    /// Fetches the PS3 game list from the Redump website.
-    async fn fetch_ps3_list_from_web(&self) -> Result<Vec<Game>> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
-
-        let response = client.get(&self.config.ps3_iso_url).send().await?;
-        
-        if !response.status().is_success() {
-            anyhow::bail!("Failed to fetch PS3 game list: HTTP {}", response.status());
+    /// Looks the search words up in the catalogue's full-text index, without loading the
+    /// whole catalogue. Returns `None` if there is no catalogue yet, it is due for a refresh
+    /// or the index found nothing, in which case callers match the search over `get_ps3_list`.
+    pub fn full_text_candidates(&self, search: &str) -> Result<Option<Vec<(usize, Game)>>> {
+        let catalog = Catalog::open(&self.config)?;
+        if catalog.is_empty()? || self.refresh_due(&catalog)? {
+            return Ok(None);
         }
+        let games = catalog.full_text_search(search)?;
+        Ok((!games.is_empty()).then_some(games))
+    }
 
-        let html_content = response.text().await?;
-        let document = Html::parse_document(&html_content);
+    /// Returns true if the cached list should be checked for changes now. Offline with only
+    /// web mirrors, the cached list is all there is.
+    fn refresh_due(&self, catalog: &Catalog) -> Result<bool> {
+        let reachable = self
+            .config
+            .platforms()
+            .into_iter()
+            .all(|game_type| !Mirrors::games(&self.config, game_type).is_empty());
+        Ok(reachable && (self.force_refresh || is_expired(catalog.last_checked_at()?, self.config.cache_ttl())))
+    }
+
+    /// Fetches the game list of every configured platform from the web and overwrites the
+    /// cache. Each request is conditional on the ETag and Last-Modified of that platform's
+    /// cached list, so an unchanged listing is not downloaded or parsed again. Mirrors are
+    /// tried in order until one answers.
+    pub async fn refresh_ps3_list(&self) -> Result<Vec<Game>> {
+        let mut catalog = Catalog::open(&self.config)?;
+        let cached_games = if catalog.is_empty()? { Vec::new() } else { catalog.load_games()? };
+        let platforms = self.config.platforms();
 
-        // Selector for PS3 game links in the table structure
-        let row_selector = Selector::parse("tbody tr").unwrap();
-        let link_selector = Selector::parse("td.link a").unwrap();
-        let size_selector = Selector::parse("td.size").unwrap();
         let mut games = Vec::new();
+        let mut listings = Vec::new();
+        // Games of a platform no longer configured must go, even if every listing is unchanged
+        let mut changed = cached_games.iter().any(|game| !platforms.contains(&game.game_type));
+        for game_type in platforms {
+            let mirrors = Mirrors::games(&self.config, game_type).with_reporter(self.reporter.clone());
+            if mirrors.is_empty() {
+                return Err(mirrors.no_source_error());
+            }
+            let cached = if cached_games.is_empty() {
+                None
+            } else {
+                catalog.validators(game_type)?
+            };
 
-        for row in document.select(&row_selector) {
-            // Skip the parent directory row
-            if let Some(link_element) = row.select(&link_selector).next() {
-                if let Some(href) = link_element.value().attr("href") {
-                    let title = link_element.text().collect::<String>().trim().to_string();
-                    
-                    // Skip if title is empty or doesn't end with .zip
-                    if title.is_empty() || !title.ends_with(".zip") {
-                        continue;
+            self.reporter
+                .message(format!("Fetching {} game list from Redump...", game_type.name()));
+            let (url, fetched) = mirrors
+                .fetch_listing(cached.as_ref().map(|(source, validators)| (source.as_str(), validators)))
+                .await
+                .map_err(|e| Error::CatalogFetch {
+                    platform: game_type.name(),
+                    url: self.config.iso_urls_for(game_type).remove(0),
+                    reason: format!("{:#}", e),
+                })?;
+            match fetched {
+                Fetched::NotModified => {
+                    let (_, validators) = cached.unwrap_or_default();
+                    games.extend(cached_games.iter().filter(|game| game.game_type == game_type).cloned());
+                    listings.push(ListingSource {
+                        game_type,
+                        source: url,
+                        validators,
+                    });
+                    self.reporter
+                        .message(format!("{} game list unchanged", game_type.name()));
+                }
+                Fetched::Changed { body, validators } => {
+                    let platform_games = parse_game_list(&body, game_type);
+                    // An error page or an unexpected layout must not wipe out the cached list
+                    if platform_games.is_empty() {
+                        return Err(Error::CatalogFetch {
+                            platform: game_type.name(),
+                            url,
+                            reason: "the listing has no .zip files".to_string(),
+                        }
+                        .into());
                     }
-
-                    // Extract size information from the size column
-                    let size = if let Some(size_element) = row.select(&size_selector).next() {
-                        size_element.text().collect::<String>().trim().to_string()
-                    } else {
-                        "Unknown size".to_string()
-                    };
-                    
-                    // Extract region information
-                    let region = self.extract_region_from_title(&title);
-
-                    let game = Game::new_ps3(
-                        title.clone(),
-                        href.to_string(),
-                        size,
-                        region,
-                    );
-
-                    games.push(game);
+                    changed = true;
+                    games.extend(platform_games);
+                    listings.push(ListingSource {
+                        game_type,
+                        source: url,
+                        validators,
+                    });
                 }
             }
         }
 
-        // Sort games by title for easier browsing
-        games.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
+        if !changed {
+            catalog.mark_unchanged()?;
+            self.reporter
+                .message(format!("Game lists unchanged, {} games in cache", cached_games.len()));
+            return Ok(cached_games);
+        }
+
+        // Save to cache
+        let diff = catalog.replace_games(&games, &listings)?;
+        
+        self.reporter.message(format!("Cached {} games", games.len()));
+        if let Some(diff) = diff.filter(|diff| !diff.changes.is_empty()) {
+            self.reporter.message(format!(
+                "Since the last refresh: {} added, {} removed, {} changed in size (see `whats-new`)",
+                diff.count(ChangeKind::Added),
+                diff.count(ChangeKind::Removed),
+                diff.count(ChangeKind::Resized)
+            ));
 
+            // Watched queries are only checked against the titles that just appeared
+            let added: HashSet<&str> = diff
+                .changes
+                .iter()
+                .filter(|change| change.kind == ChangeKind::Added)
+                .map(|change| change.title.as_str())
+                .collect();
+            let alerts = catalog.load_games().and_then(|listed| {
+                let new_games: Vec<Game> = listed
+                    .into_iter()
+                    .filter(|game| added.contains(game.title.as_str()))
+                    .collect();
+                check_watchlist(&self.config, &catalog, &new_games, AlertReason::Listed)
+            });
+            match alerts {
+                Ok(alerts) => report_alerts(&self.reporter, &alerts),
+                Err(e) => tracing::warn!("Failed to check the watchlist: {:#}", e),
+            }
+        }
         Ok(games)
     }
 
-    /// Extracts region information from the game title.
-    fn extract_region_from_title(&self, title: &str) -> Option<String> {
-        let regions = ["USA", "Europe", "Japan", "Asia", "Australia", "PAL", "NTSC"];
-        
-        for region in &regions {
-            if title.contains(region) {
-                return Some(region.to_string());
-            }
+
+    /// Imports the JSON game list cache of older versions into an empty catalogue database,
+    /// then renames it so it is not imported again. Returns `None` if there is no usable cache.
+    fn migrate_json_cache(&self, catalog: &mut Catalog) -> Result<Option<Vec<Game>>> {
+        let json_path = self.config.list_ps3_json_path();
+        if !json_path.exists() {
+            return Ok(None);
         }
-        
-        None
+        let Ok(games) = self.load_from_cache(&json_path) else {
+            return Ok(None);
+        };
+
+        let listing = ListingSource {
+            game_type: GameType::PS3,
+            source: format!("file://{}", json_path.display()),
+            validators: Validators::default(),
+        };
+        catalog.replace_games(&games, &[listing])?;
+        let mut migrated = json_path.clone().into_os_string();
+        migrated.push(".migrated");
+        fs::rename(&json_path, &migrated).map_err(|e| Error::filesystem("rename", &json_path, e))?;
+        self.reporter.message(format!(
+            "Moved {} PS3 games from {} to {}",
+            games.len(),
+            json_path.display(),
+            catalog.path().display()
+        ));
+        Ok(Some(games))
     }
 
     /// Loads the game list from the JSON cache file.
@@ -120,16 +241,42 @@ impl Scraper {
         let content = fs::read_to_string(json_path)?;
         let games: Vec<Game> = serde_json::from_str(&content)?;
         
-        // Ensure all games have lowercased_title set
-        let games = games.into_iter().map(|game| game.with_lowercased()).collect();
+        // Ensure all games have lowercased_title, the parsed title fields and the size in bytes set
+        let games: Vec<Game> = games
+            .into_iter()
+            .map(|game| game.with_lowercased().with_parsed_name().with_size_bytes())
+            .collect();
         
         Ok(games)
     }
+}
 
-    /// Saves the game list to the JSON cache file.
-    fn save_to_cache(&self, json_path: &Path, games: &[Game]) -> Result<()> {
-        let json_content = serde_json::to_string_pretty(games)?;
-        fs::write(json_path, json_content)?;
-        Ok(())
-    }
+/// Parses a platform's game list from a directory listing of its Redump set, in any of the
+/// layouts `parse_listing` detects.
+fn parse_game_list(html_content: &str, game_type: GameType) -> Vec<Game> {
+    let mut games: Vec<Game> = parse_listing(html_content)
+        .into_iter()
+        // Only the .zip archives are games
+        .filter(|entry| entry.name.ends_with(".zip"))
+        .map(|entry| {
+            let size = if entry.size.is_empty() {
+                "Unknown size".to_string()
+            } else {
+                entry.size
+            };
+            // Regions, languages and other tags are parsed from the title
+            let mut game = Game::new(game_type, entry.name, entry.href, size, None).with_date(&entry.date);
+            if entry.size_bytes.is_some() {
+                game.size_bytes = entry.size_bytes;
+            }
+            game.zip_md5 = entry.md5;
+            game.zip_sha1 = entry.sha1;
+            game
+        })
+        .collect();
+
+    // Sort games by title for easier browsing
+    games.sort_by_key(|game| game.title.to_lowercase());
+
+    games
 }
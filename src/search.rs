@@ -0,0 +1,336 @@
+use crate::{dat::is_serial, models::Game};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Common abbreviations and the title words they stand for.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("ac", "assassins creed"),
+    ("bf", "battlefield"),
+    ("cod", "call of duty"),
+    ("dbz", "dragon ball z"),
+    ("dmc", "devil may cry"),
+    ("ff", "final fantasy"),
+    ("gow", "god of war"),
+    ("gt", "gran turismo"),
+    ("gta", "grand theft auto"),
+    ("kh", "kingdom hearts"),
+    ("lbp", "littlebigplanet"),
+    ("mgs", "metal gear solid"),
+    ("mk", "mortal kombat"),
+    ("nfs", "need for speed"),
+    ("rdr", "red dead redemption"),
+    ("re", "resident evil"),
+    ("sf", "street fighter"),
+    ("tlou", "last of us"),
+    ("vs", "versus"),
+];
+
+/// Roman numerals written as a single letter, which `normalize` keeps as letters, and their
+/// values. A title word "v" still matches a search for "5".
+const LETTER_NUMERALS: [(&str, &str); 3] = [("i", "1"), ("v", "5"), ("x", "10")];
+
+/// Precomputed search form of a title.
+#[derive(Debug, Clone, Default)]
+pub struct SearchKey {
+    /// Normalised words of the whole title (see `normalize`)
+    pub words: Vec<String>,
+    /// How many of `words` belong to the name, before the first parenthesis (region, languages...)
+    pub name_words: usize,
+}
+
+impl SearchKey {
+    /// Normalises a title for searching.
+    pub fn new(title: &str) -> Self {
+        let name = title.split('(').next().unwrap_or(title);
+        Self {
+            name_words: normalize(name).len(),
+            words: normalize(title),
+        }
+    }
+}
+
+/// A search hit: the game, its 1-based catalogue index and how well it matched.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchMatch<'a> {
+    /// 1-based position in the catalogue
+    pub index: usize,
+    pub game: &'a Game,
+    /// Relevance from 0 to 100; 100 means every search word matched a word of the name
+    /// exactly and the search covers the whole name
+    pub score: f64,
+}
+
+/// Filters PS3 games by search string, best matches first (see `search_games`).
+pub fn filter_games<'a>(games: &'a [Game], search: &str) -> Vec<&'a Game> {
+    search_games(games, search)
+        .into_iter()
+        .map(|hit| hit.game)
+        .collect()
+}
+
+/// Same as `filter_games`, but keeps the 1-based catalogue index of every match.
+pub fn filter_games_indexed<'a>(games: &'a [Game], search: &str) -> Vec<(usize, &'a Game)> {
+    search_games(games, search)
+        .into_iter()
+        .map(|hit| (hit.index, hit.game))
+        .collect()
+}
+
+/// Finds the games matching every word of the search, ordered by relevance.
+///
+/// Titles and search are normalised the same way (see `normalize`), and each search word
+/// may match a title word exactly, as a prefix, inside a word, with a small typo, or through
+/// a common abbreviation ("ff" for "final fantasy"). An empty search returns the whole
+/// catalogue in catalogue order.
+pub fn search_games<'a>(games: &'a [Game], search: &str) -> Vec<SearchMatch<'a>> {
+    let indexed: Vec<(usize, &Game)> = games.iter().enumerate().map(|(i, game)| (i + 1, game)).collect();
+    search_indexed(&indexed, search)
+}
+
+/// Same as `search_games`, over games that already carry their 1-based catalogue index
+/// (e.g. the candidates of a query, see `query_candidates`).
+pub fn search_indexed<'a>(games: &[(usize, &'a Game)], search: &str) -> Vec<SearchMatch<'a>> {
+    // A serial or title ID ("BLUS-30001") finds the games it belongs to, once a DAT is imported
+    if is_serial(search) {
+        return games
+            .iter()
+            .filter(|(_, game)| game.has_serial(search))
+            .map(|&(index, game)| SearchMatch { index, game, score: 100.0 })
+            .collect();
+    }
+
+    let query: Vec<String> = normalize(search);
+
+    let mut matches: Vec<SearchMatch> = games
+        .iter()
+        .filter_map(|&(index, game)| {
+            score_tokens(&query, &game.search_key).map(|score| SearchMatch { index, game, score })
+        })
+        .collect();
+
+    // Stable sort, so equal scores keep catalogue order
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches
+}
+
+/// Scores normalised search words against a title. Returns `None` unless every search word
+/// matches. 90% of the score is how well the words match, 10% how much of the name they cover,
+/// so "gt5" ranks "Gran Turismo 5" above "Gran Turismo 5 Prologue".
+pub fn score_tokens(query: &[String], key: &SearchKey) -> Option<f64> {
+    if query.is_empty() {
+        return Some(0.0);
+    }
+    let title = &key.words;
+    let joined: String = title.concat();
+    let mut covered = vec![false; key.name_words];
+
+    let mut total = 0.0;
+    for word in query {
+        let expansion = ABBREVIATIONS
+            .iter()
+            .find(|(short, _)| short == word)
+            .map(|(_, expansion)| expansion.split(' ').collect::<Vec<_>>())
+            .unwrap_or_default();
+        for (name_word, covered) in title.iter().zip(covered.iter_mut()) {
+            if name_word == word || is_letter_numeral(name_word, word) || expansion.contains(&name_word.as_str()) {
+                *covered = true;
+            }
+        }
+
+        let mut best = word_score(word, title, &joined);
+        if !expansion.is_empty() {
+            let scores: Vec<f64> = expansion.iter().map(|w| word_score(w, title, &joined)).collect();
+            if scores.iter().all(|score| *score > 0.0) {
+                best = best.max(scores.iter().sum::<f64>() / scores.len() as f64);
+            }
+        }
+        if best == 0.0 {
+            return None;
+        }
+        total += best;
+    }
+
+    let matched = total / query.len() as f64;
+    let coverage = if covered.is_empty() {
+        1.0
+    } else {
+        covered.iter().filter(|c| **c).count() as f64 / covered.len() as f64
+    };
+    Some(((matched * 90.0 + coverage * 10.0) * 10.0).round() / 10.0)
+}
+
+/// Scores one search word against the title words, from 0 (no match) to 1 (exact word).
+fn word_score(word: &str, title: &[String], joined: &str) -> f64 {
+    let numeric = word.chars().all(|c| c.is_ascii_digit());
+    let max_typos = match word.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+
+    let mut best: f64 = 0.0;
+    for title_word in title {
+        if title_word == word || is_letter_numeral(title_word, word) {
+            return 1.0;
+        }
+        // Numbers must match exactly, so "2" does not find "20"
+        if numeric {
+            continue;
+        }
+        if title_word.starts_with(word) {
+            best = best.max(0.9);
+        } else if word.len() >= 3 && title_word.contains(word) {
+            best = best.max(0.7);
+        } else if max_typos > 0 {
+            let typos = strsim::damerau_levenshtein(word, title_word);
+            // A typo in a partially typed word: compare with the start of the title word
+            let prefix: String = title_word.chars().take(word.chars().count()).collect();
+            let typos = typos.min(strsim::damerau_levenshtein(word, &prefix) + 1);
+            if typos <= max_typos {
+                best = best.max(0.7 - 0.1 * typos as f64);
+            }
+        }
+    }
+
+    // Words written together or apart ("little big planet" vs "LittleBigPlanet")
+    if best == 0.0 && !numeric && word.len() >= 3 && joined.contains(word) {
+        best = 0.6;
+    }
+    best
+}
+
+/// Builds the full-text index expression finding the titles that hold every search word as
+/// a word prefix, as `normalize` writes them; a number also finds its single-letter Roman
+/// numeral. Returns `None` when the index cannot stand in for the search: for an empty
+/// search, a serial or an abbreviation ("gta"), which only the full matching understands.
+pub fn full_text_expression(search: &str) -> Option<String> {
+    let words = normalize(search);
+    if words.is_empty()
+        || is_serial(search)
+        || words.iter().any(|word| ABBREVIATIONS.iter().any(|(short, _)| short == word))
+    {
+        return None;
+    }
+    // Quoted, so words are never read as FTS operators
+    let expression = words
+        .iter()
+        .map(|word| match LETTER_NUMERALS.iter().find(|(_, value)| value == word) {
+            Some((letter, _)) => format!("(\"{}\"* OR \"{}\")", word, letter),
+            None => format!("\"{}\"*", word),
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    Some(expression)
+}
+
+/// Returns true if a title word is a single-letter Roman numeral ("v") and the search word
+/// its value ("5").
+fn is_letter_numeral(title_word: &str, word: &str) -> bool {
+    LETTER_NUMERALS.contains(&(title_word, word))
+}
+
+/// Splits a title or search into comparable words: lowercased, diacritics folded
+/// ("Pokémon" → "pokemon"), "&" read as "and", apostrophes dropped, other punctuation
+/// treated as a separator, letters and digits split ("ff13" → "ff", "13") and
+/// Roman numerals of two or more letters, up to 39, turned into Arabic ones ("XIII" → "13").
+pub fn normalize(text: &str) -> Vec<String> {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            '&' => folded.push_str(" and "),
+            '\'' | '’' | '`' => {}
+            c if c.is_alphanumeric() => folded.extend(c.to_lowercase()),
+            _ => folded.push(' '),
+        }
+    }
+
+    let mut words = Vec::new();
+    for chunk in folded.split_whitespace() {
+        let mut word = String::new();
+        let mut digits = false;
+        for c in chunk.chars() {
+            if !word.is_empty() && c.is_ascii_digit() != digits {
+                words.push(std::mem::take(&mut word));
+            }
+            digits = c.is_ascii_digit();
+            word.push(c);
+        }
+        words.push(word);
+    }
+
+    words
+        .into_iter()
+        .map(|word| match roman_to_arabic(&word) {
+            Some(value) => value.to_string(),
+            // "007" and "7" are the same number
+            None => match word.parse::<u64>() {
+                Ok(number) => number.to_string(),
+                Err(_) => word,
+            },
+        })
+        .collect()
+}
+
+/// Converts a lowercase Roman numeral from 2 to 39 (i, v and x only) to its value. Single
+/// letters are left alone, as "i", "v" and "x" are often words or letters in titles; they
+/// match their value when scored instead (see `LETTER_NUMERALS`).
+fn roman_to_arabic(word: &str) -> Option<u32> {
+    if word.len() < 2 || !word.chars().all(|c| matches!(c, 'i' | 'v' | 'x')) {
+        return None;
+    }
+    let values: Vec<u32> = word
+        .chars()
+        .map(|c| match c {
+            'i' => 1,
+            'v' => 5,
+            _ => 10,
+        })
+        .collect();
+    let mut total = 0;
+    for (i, value) in values.iter().enumerate() {
+        match values.get(i + 1) {
+            Some(next) if next > value => total -= *value as i32,
+            _ => total += *value as i32,
+        }
+    }
+
+    // Only accept the canonical spelling, so words like "xiv" pass but "iix" does not
+    let value = u32::try_from(total).ok().filter(|v| (1..=39).contains(v))?;
+    (arabic_to_roman(value) == word).then_some(value)
+}
+
+/// Writes a number from 1 to 39 as a lowercase Roman numeral.
+fn arabic_to_roman(mut value: u32) -> String {
+    let mut roman = String::new();
+    for (amount, symbol) in [(10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")] {
+        while value >= amount {
+            roman.push_str(symbol);
+            value -= amount;
+        }
+    }
+    roman
+}
+
+/// Resolves a game from an exact title (with or without .zip), a 1-based catalogue index,
+/// or a serial or title ID from the imported DAT.
+pub fn find_game<'a>(games: &'a [Game], target: &str) -> Option<&'a Game> {
+    let target = target.trim();
+
+    if let Ok(index) = target.parse::<usize>() {
+        return index.checked_sub(1).and_then(|i| games.get(i));
+    }
+
+    if is_serial(target) {
+        return games.iter().find(|game| game.has_serial(target));
+    }
+
+    games
+        .iter()
+        .find(|game| game.title == target || game.clean_title() == target)
+        .or_else(|| {
+            let target_lower = target.to_lowercase();
+            games.iter().find(|game| {
+                game.lowercased_title == target_lower
+                    || game.clean_title().to_lowercase() == target_lower
+            })
+        })
+}
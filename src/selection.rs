@@ -0,0 +1,47 @@
+use anyhow::Result;
+
+/// Parses a selection such as `3`, `1,3,5-9` or `all` into 0-based indexes.
+/// Indexes are 1-based in the input, kept in the order given and deduplicated.
+pub fn parse_selection(input: &str, max: usize) -> Result<Vec<usize>> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("all") || input == "*" {
+        return Ok((0..max).collect());
+    }
+
+    let mut selected = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (parse_number(start)?, parse_number(end)?),
+            None => {
+                let number = parse_number(part)?;
+                (number, number)
+            }
+        };
+
+        if start > end {
+            anyhow::bail!("Invalid range '{}': start is greater than end", part);
+        }
+        if start == 0 || end > max {
+            anyhow::bail!("'{}' is not in valid range (1-{})", part, max);
+        }
+
+        for number in start..=end {
+            if !selected.contains(&(number - 1)) {
+                selected.push(number - 1);
+            }
+        }
+    }
+
+    if selected.is_empty() {
+        anyhow::bail!("Nothing selected");
+    }
+
+    Ok(selected)
+}
+
+fn parse_number(value: &str) -> Result<usize> {
+    value
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| anyhow::anyhow!("'{}' is not a number", value.trim()))
+}
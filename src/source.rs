@@ -0,0 +1,609 @@
+use crate::{
+    config::Config,
+    error::Error,
+    listing::{fetch_listing, Fetched, Validators},
+    models::GameType,
+    progress::Reporter,
+    utils::format_timestamp,
+};
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures_util::{FutureExt, StreamExt, TryStreamExt};
+use percent_encoding::percent_decode_str;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use std::fs;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// A listed file opened for reading.
+pub struct RemoteFile {
+    /// Where the file is read from
+    pub url: String,
+    /// Byte the stream starts at: the requested offset, or 0 if the source cannot resume
+    pub offset: u64,
+    /// Size of the whole file, if the source tells
+    pub total_size: Option<u64>,
+    pub stream: BoxStream<'static, Result<Vec<u8>>>,
+}
+
+/// A place a listing and its files are fetched from, such as one mirror of the Redump files.
+pub trait Source: Send + Sync {
+    /// Where the source points, e.g. its base URL, for messages and the catalogue's refresh log.
+    fn location(&self) -> &str;
+
+    /// Returns the address of a listed file, from its link in the listing.
+    fn file_url(&self, link: &str) -> String;
+
+    /// Fetches the listing, conditionally if validators of an earlier fetch from this source are given.
+    fn fetch_listing<'a>(&'a self, validators: Option<&'a Validators>) -> BoxFuture<'a, Result<Fetched>>;
+
+    /// Returns the size of a listed file, if the source tells, without reading it.
+    fn file_size<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Option<u64>>>;
+
+    /// Opens a listed file for reading from `offset` on.
+    fn open_file<'a>(&'a self, link: &'a str, offset: u64) -> BoxFuture<'a, Result<RemoteFile>>;
+
+    /// Returns the path of a listed file if the source is on a local or mounted disk.
+    fn local_path(&self, _link: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// An HTTP(S) directory listing, such as Myrient's.
+pub struct HttpSource {
+    /// Base URL ending in '/', which listed links are relative to
+    base_url: String,
+    /// Timeout for file downloads (seconds)
+    timeout: u64,
+}
+
+impl HttpSource {
+    /// Creates a source for the listing at `base_url`.
+    pub fn new(base_url: &str, config: &Config) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            timeout: config.timeout_request.unwrap_or(1800), // Longer timeout for PS3 files
+        }
+    }
+
+    async fn get_file_size(&self, link: &str) -> Result<Option<u64>> {
+        let url = self.file_url(link);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+
+        let response = client
+            .get(&url)
+            .header(RANGE, "bytes=0-1")
+            .send()
+            .await
+            .map_err(|e| http_error(&url, e))?;
+
+        // An error page's content-length is not the size of the file
+        if !response.status().is_success() {
+            return Err(Error::Http {
+                url,
+                reason: format!("HTTP {}", response.status()),
+            }
+            .into());
+        }
+
+        // Try the total of the content-range first, then content-length as fallback
+        Ok(content_range_total(response.headers()).or_else(|| content_length(response.headers())))
+    }
+
+    async fn open(&self, link: &str, offset: u64) -> Result<RemoteFile> {
+        let url = self.file_url(link);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout))
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+
+        let mut request = client.get(&url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        let response = request.send().await.map_err(|e| http_error(&url, e))?;
+        if !response.status().is_success() {
+            return Err(Error::Http {
+                url,
+                reason: format!("HTTP {}", response.status()),
+            }
+            .into());
+        }
+
+        // A server that ignores the range sends the whole file again
+        let offset = if response.status() == StatusCode::PARTIAL_CONTENT { offset } else { 0 };
+        let total_size = content_range_total(response.headers())
+            .or_else(|| content_length(response.headers()).map(|length| offset + length));
+        let stream_url = url.clone();
+        let stream = response
+            .bytes_stream()
+            .map_ok(|chunk| chunk.to_vec())
+            .map_err(move |e| http_error(&stream_url, e).into())
+            .boxed();
+        Ok(RemoteFile {
+            url,
+            offset,
+            total_size,
+            stream,
+        })
+    }
+}
+
+impl Source for HttpSource {
+    fn location(&self) -> &str {
+        &self.base_url
+    }
+
+    fn file_url(&self, link: &str) -> String {
+        format!("{}{}", self.base_url, link)
+    }
+
+    fn fetch_listing<'a>(&'a self, validators: Option<&'a Validators>) -> BoxFuture<'a, Result<Fetched>> {
+        fetch_listing(&self.base_url, validators).boxed()
+    }
+
+    fn file_size<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        self.get_file_size(link).boxed()
+    }
+
+    fn open_file<'a>(&'a self, link: &'a str, offset: u64) -> BoxFuture<'a, Result<RemoteFile>> {
+        self.open(link, offset).boxed()
+    }
+}
+
+/// A directory on a local or mounted disk, such as a NAS copy of the Redump set.
+/// Configured as a plain path or a `file://` URL.
+pub struct LocalSource {
+    /// The configured path or URL
+    location: String,
+    root: PathBuf,
+}
+
+impl LocalSource {
+    /// Creates a source for the files in `root`.
+    pub fn new(location: &str, root: PathBuf) -> Self {
+        Self {
+            location: location.to_string(),
+            root,
+        }
+    }
+
+    /// Returns the path of a listed file; links are percent-encoded file names.
+    fn path(&self, link: &str) -> PathBuf {
+        self.root.join(percent_decode_str(link).decode_utf8_lossy().as_ref())
+    }
+
+    /// Lists the files of the directory as an nginx JSON listing, which `parse_listing`
+    /// reads like any other. The ETag sums up the names, sizes and modification times, so
+    /// an unchanged directory is not parsed again.
+    fn list(&self, validators: Option<&Validators>) -> Result<Fetched> {
+        let entries = fs::read_dir(&self.root).map_err(|e| Error::filesystem("read", &self.root, e))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| Error::filesystem("read", &self.root, e))?;
+            let metadata = entry.metadata().map_err(|e| Error::filesystem("read", &entry.path(), e))?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs());
+            files.push((entry.file_name().to_string_lossy().into_owned(), metadata.len(), modified));
+        }
+        files.sort();
+
+        let etag = format!(
+            "\"{}-{}-{}\"",
+            files.len(),
+            files.iter().map(|(_, size, _)| size).sum::<u64>(),
+            files.iter().map(|(_, _, modified)| modified).max().unwrap_or(&0)
+        );
+        if validators.and_then(|validators| validators.etag.as_deref()) == Some(etag.as_str()) {
+            return Ok(Fetched::NotModified);
+        }
+        let listing: Vec<serde_json::Value> = files
+            .into_iter()
+            .map(|(name, size, modified)| {
+                serde_json::json!({ "name": name, "type": "file", "mtime": format_timestamp(modified), "size": size })
+            })
+            .collect();
+        Ok(Fetched::Changed {
+            body: serde_json::to_string(&listing)?,
+            validators: Validators {
+                etag: Some(etag),
+                last_modified: None,
+            },
+        })
+    }
+
+    async fn open(&self, link: &str, offset: u64) -> Result<RemoteFile> {
+        let path = self.path(link);
+        let mut file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|e| Error::filesystem("open", &path, e))?;
+        let total_size = file.metadata().await.ok().map(|metadata| metadata.len());
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| Error::filesystem("read", &path, e))?;
+
+        let stream = futures::stream::try_unfold((file, path.clone()), |(mut file, path)| async move {
+            let mut buffer = vec![0; 1 << 20];
+            let read = file
+                .read(&mut buffer)
+                .await
+                .map_err(|e| Error::filesystem("read", &path, e))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            buffer.truncate(read);
+            Ok::<_, anyhow::Error>(Some((buffer, (file, path))))
+        })
+        .boxed();
+        Ok(RemoteFile {
+            url: path.display().to_string(),
+            offset,
+            total_size,
+            stream,
+        })
+    }
+}
+
+impl Source for LocalSource {
+    fn location(&self) -> &str {
+        &self.location
+    }
+
+    fn file_url(&self, link: &str) -> String {
+        self.path(link).display().to_string()
+    }
+
+    fn fetch_listing<'a>(&'a self, validators: Option<&'a Validators>) -> BoxFuture<'a, Result<Fetched>> {
+        futures::future::ready(self.list(validators)).boxed()
+    }
+
+    fn file_size<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        let path = self.path(link);
+        let size = fs::metadata(&path)
+            .map(|metadata| Some(metadata.len()))
+            .map_err(|e| Error::filesystem("read", &path, e).into());
+        futures::future::ready(size).boxed()
+    }
+
+    fn open_file<'a>(&'a self, link: &'a str, offset: u64) -> BoxFuture<'a, Result<RemoteFile>> {
+        self.open(link, offset).boxed()
+    }
+
+    fn local_path(&self, link: &str) -> Option<PathBuf> {
+        Some(self.path(link))
+    }
+}
+
+/// An Internet Archive item holding a copy of the Redump set. Files are listed from the
+/// item's metadata JSON, with exact sizes and hashes, and downloaded from
+/// `archive.org/download/<identifier>/`. Configured as the item's `details`, `download` or
+/// `metadata` URL (a `download` URL may name a folder of the item), or as the path of a
+/// saved copy of the metadata JSON.
+pub struct ArchiveSource {
+    /// The configured URL or path
+    location: String,
+    /// Where the metadata JSON is read from: its URL, or the path of a saved copy
+    metadata: String,
+    /// Folder of the item the listing is limited to, "" or ending in '/'
+    folder: String,
+    /// Downloads go through the item's download URL
+    files: HttpSource,
+}
+
+impl ArchiveSource {
+    /// Creates a source for the item at `archive.org/<details|download|metadata>/<identifier>[/folder/]`,
+    /// or `None` if the URL is not an Internet Archive item.
+    pub fn from_url(url: &str, config: &Config) -> Option<Self> {
+        let (_, path) = url.split_once("archive.org/")?;
+        let mut segments = path.trim_end_matches('/').splitn(3, '/');
+        if !matches!(segments.next()?, "details" | "download" | "metadata") {
+            return None;
+        }
+        let identifier = segments.next().filter(|identifier| !identifier.is_empty())?;
+        let folder = segments.next().map(|folder| format!("{}/", folder)).unwrap_or_default();
+        Some(Self::new(url, &format!("https://archive.org/metadata/{}", identifier), identifier, &folder, config))
+    }
+
+    /// Creates a source listing a saved copy of an item's metadata JSON, downloading from the
+    /// item it names.
+    pub fn from_saved_metadata(location: &str, path: &Path, config: &Config) -> Self {
+        // The identifier is needed for download URLs before anything is listed; a copy
+        // that cannot be read fails again, with its error, when it is listed
+        let identifier = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|metadata| metadata["metadata"]["identifier"].as_str().map(str::to_string))
+            .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+        Self::new(location, &path.display().to_string(), &identifier, "", config)
+    }
+
+    fn new(location: &str, metadata: &str, identifier: &str, folder: &str, config: &Config) -> Self {
+        let download_url = format!("https://archive.org/download/{}/{}", identifier, folder);
+        Self {
+            location: location.to_string(),
+            metadata: metadata.to_string(),
+            // Metadata names are not percent-encoded, unlike a folder copied from a URL
+            folder: percent_decode_str(folder).decode_utf8_lossy().into_owned(),
+            files: HttpSource::new(&download_url, config),
+        }
+    }
+
+    /// Fetches the metadata JSON and keeps only the files in the configured folder, with
+    /// their names relative to it.
+    async fn list(&self, validators: Option<&Validators>) -> Result<Fetched> {
+        let (body, validators) = if self.metadata.contains("://") {
+            match fetch_listing(&self.metadata, validators).await? {
+                Fetched::NotModified => return Ok(Fetched::NotModified),
+                Fetched::Changed { body, validators } => (body, validators),
+            }
+        } else {
+            let path = Path::new(&self.metadata);
+            let body = fs::read_to_string(path).map_err(|e| Error::filesystem("read", path, e))?;
+            (body, Validators::default())
+        };
+
+        let mut metadata: serde_json::Value =
+            serde_json::from_str(&body).map_err(|e| anyhow::anyhow!("Invalid metadata JSON from {}: {}", self.metadata, e))?;
+        if let Some(files) = metadata["files"].as_array_mut() {
+            files.retain_mut(|file| {
+                let Some(name) = file["name"].as_str().and_then(|name| name.strip_prefix(&self.folder)) else {
+                    return false;
+                };
+                file["name"] = serde_json::Value::String(name.to_string());
+                true
+            });
+        }
+        Ok(Fetched::Changed {
+            body: serde_json::to_string(&metadata)?,
+            validators,
+        })
+    }
+}
+
+impl Source for ArchiveSource {
+    fn location(&self) -> &str {
+        &self.location
+    }
+
+    fn file_url(&self, link: &str) -> String {
+        self.files.file_url(link)
+    }
+
+    fn fetch_listing<'a>(&'a self, validators: Option<&'a Validators>) -> BoxFuture<'a, Result<Fetched>> {
+        self.list(validators).boxed()
+    }
+
+    fn file_size<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        self.files.file_size(link)
+    }
+
+    fn open_file<'a>(&'a self, link: &'a str, offset: u64) -> BoxFuture<'a, Result<RemoteFile>> {
+        self.files.open_file(link, offset)
+    }
+}
+
+/// Returns the directory a configured source URL points to, if it is a `file://` URL or
+/// a plain path rather than a web address.
+pub fn local_root(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(percent_decode_str(path).decode_utf8_lossy().as_ref()));
+    }
+    (!url.contains("://")).then(|| Config::expand_tilde(url))
+}
+
+/// The configured mirrors of one listing, tried in order: a fetch that fails on one mirror
+/// falls through to the next.
+pub struct Mirrors {
+    sources: Vec<Box<dyn Source>>,
+    /// Web mirrors left out because of `--offline`
+    skipped: Vec<String>,
+    reporter: Reporter,
+}
+
+impl Mirrors {
+    /// Mirrors of a platform's game list (`PS3_ISO`, then `PS3_ISO_MIRRORS`, for PS3).
+    pub fn games(config: &Config, game_type: GameType) -> Self {
+        Self::from_urls(&config.iso_urls_for(game_type), config)
+    }
+
+    /// Mirrors of the PS3 keys list (`PS3_KEYS`, then `PS3_KEYS_MIRRORS`).
+    pub fn keys(config: &Config) -> Self {
+        Self::from_urls(&config.keys_urls(), config)
+    }
+
+    /// Creates a local source for paths and `file://` URLs, an Internet Archive source for
+    /// archive.org items and saved item metadata (`.json`), and a web source for the rest.
+    /// Sources that need the network are left out when offline.
+    fn from_urls(urls: &[String], config: &Config) -> Self {
+        let mut sources: Vec<Box<dyn Source>> = Vec::new();
+        let mut skipped = Vec::new();
+        for url in urls {
+            let root = local_root(url);
+            let saved_metadata = root.as_ref().is_some_and(|root| root.extension().is_some_and(|ext| ext == "json"));
+            // Internet Archive items are downloaded over the network even when listed from a saved copy
+            if config.offline && (root.is_none() || saved_metadata) {
+                skipped.push(url.clone());
+                continue;
+            }
+            let source: Box<dyn Source> = match root {
+                Some(root) if saved_metadata => Box::new(ArchiveSource::from_saved_metadata(url, &root, config)),
+                Some(root) => Box::new(LocalSource::new(url, root)),
+                None => match ArchiveSource::from_url(url, config) {
+                    Some(source) => Box::new(source),
+                    None => Box::new(HttpSource::new(url, config)),
+                },
+            };
+            sources.push(source);
+        }
+        Self {
+            sources,
+            skipped,
+            reporter: Reporter::console(),
+        }
+    }
+
+    /// Routes failover messages through the given reporter.
+    pub fn with_reporter(mut self, reporter: Reporter) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Returns how many mirrors are configured.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns true if no mirror can be used, e.g. offline with only web mirrors.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Returns the address of a listed file on the first mirror.
+    pub fn file_url(&self, link: &str) -> String {
+        self.sources
+            .first()
+            .map(|source| source.file_url(link))
+            .unwrap_or_else(|| link.to_string())
+    }
+
+    /// Returns the path of a listed file on the first local mirror that has it.
+    pub fn local_file(&self, link: &str) -> Option<PathBuf> {
+        self.sources
+            .iter()
+            .filter_map(|source| source.local_path(link))
+            .find(|path| path.is_file())
+    }
+
+    /// Fetches the listing from the first mirror that answers, and returns it with that
+    /// mirror's location. The cached validators are only sent to the mirror they came from.
+    pub async fn fetch_listing(&self, cached: Option<(&str, &Validators)>) -> Result<(String, Fetched)> {
+        self.first_success(|source| {
+            let validators = cached
+                .filter(|(location, _)| *location == source.location())
+                .map(|(_, validators)| validators);
+            source.fetch_listing(validators)
+        })
+        .await
+        .map(|(index, fetched)| (self.sources[index].location().to_string(), fetched))
+    }
+
+    /// Returns the size of a listed file from the first mirror that answers.
+    pub async fn file_size(&self, link: &str) -> Result<Option<u64>> {
+        self.first_success(|source| source.file_size(link))
+            .await
+            .map(|(_, size)| size)
+    }
+
+    /// Opens a listed file on the first mirror that answers, starting with mirror `first`
+    /// and wrapping around. Returns the index of the mirror used with the opened file.
+    pub async fn open_file(&self, link: &str, offset: u64, first: usize) -> Result<(usize, RemoteFile)> {
+        let mut last_error = None;
+        for step in 0..self.sources.len() {
+            let index = (first + step) % self.sources.len();
+            match self.sources[index].open_file(link, offset).await {
+                Ok(file) => return Ok((index, file)),
+                Err(e) => {
+                    self.report_failure(index, &e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| self.no_source_error()))
+    }
+
+    /// Reads a small listed file whole, e.g. a key ZIP.
+    pub async fn read_file(&self, link: &str) -> Result<Vec<u8>> {
+        let mut last_error = None;
+        for index in 0..self.sources.len() {
+            let read = async {
+                let mut file = self.sources[index].open_file(link, 0).await?;
+                let mut content = Vec::new();
+                while let Some(chunk) = file.stream.next().await {
+                    content.extend_from_slice(&chunk?);
+                }
+                Ok::<_, anyhow::Error>(content)
+            };
+            match read.await {
+                Ok(content) => return Ok(content),
+                Err(e) => {
+                    self.report_failure(index, &e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| self.no_source_error()))
+    }
+
+    /// Runs the fetch on each mirror in order until one succeeds; returns the last error if none does.
+    async fn first_success<'a, T, F>(&'a self, fetch: F) -> Result<(usize, T)>
+    where
+        F: Fn(&'a dyn Source) -> BoxFuture<'a, Result<T>>,
+    {
+        let mut last_error = None;
+        for (index, source) in self.sources.iter().enumerate() {
+            match fetch(source.as_ref()).await {
+                Ok(value) => return Ok((index, value)),
+                Err(e) => {
+                    self.report_failure(index, &e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| self.no_source_error()))
+    }
+
+    /// The error when there is no mirror to try.
+    pub fn no_source_error(&self) -> anyhow::Error {
+        match self.skipped.first() {
+            Some(url) => Error::Offline { url: url.clone() }.into(),
+            None => anyhow::anyhow!("No mirror is configured"),
+        }
+    }
+
+    /// Reports a failed mirror when there are others to fall through to.
+    fn report_failure(&self, index: usize, error: &anyhow::Error) {
+        if self.sources.len() > 1 {
+            self.reporter.message(format!(
+                "Mirror {} failed: {:#}",
+                self.sources[index].location(),
+                error
+            ));
+        }
+    }
+}
+
+fn http_error(url: &str, error: reqwest::Error) -> Error {
+    Error::Http {
+        url: url.to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Reads the total size from a `Content-Range: bytes 0-1/12345` header.
+fn content_range_total(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .split('/')
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+fn content_length(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
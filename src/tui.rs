@@ -0,0 +1,564 @@
+use crate::{
+    config::Config,
+    display::describe_total_size,
+    downloader::Downloader,
+    models::Game,
+    progress::{Phase, ProgressEvent, Reporter},
+    query::query_games,
+    queue::{download_queue, QueueResult},
+    utils::format_size,
+};
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::StreamExt;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Clear, Gauge, Paragraph, Row, Table, TableState, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Number of log lines kept for the progress view.
+const LOG_LINES: usize = 200;
+
+/// Runs the full-screen catalogue browser until the user quits.
+pub async fn run_tui(config: &Config, games: Vec<Game>) -> Result<()> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let downloader = Arc::new(Downloader::new(config).with_reporter(Reporter::channel(sender)));
+    let mut app = App::new(games, downloader, config.external_iso_download);
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal, receiver).await;
+    ratatui::restore();
+
+    if let Some(job) = &app.job {
+        if !job.results.is_empty() {
+            crate::queue::print_queue_summary(&job.results);
+        }
+    }
+
+    result
+}
+
+/// A queue of downloads running in the background.
+struct Job {
+    handle: Option<JoinHandle<Vec<QueueResult>>>,
+    /// Title of the game being processed, with its 1-based queue position
+    current: Option<(usize, usize, String)>,
+    phase: Option<Phase>,
+    done: u64,
+    total: Option<u64>,
+    log: Vec<String>,
+    results: Vec<QueueResult>,
+}
+
+impl Job {
+    fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+}
+
+struct App {
+    games: Vec<Game>,
+    downloader: Arc<Downloader>,
+    external_download: bool,
+    query: String,
+    /// Why the query could not be parsed; the previous results stay listed meanwhile
+    query_error: Option<String>,
+    /// Catalogue indexes (0-based) matching the query
+    filtered: Vec<usize>,
+    table_state: TableState,
+    /// Marked catalogue indexes, in the order they were marked
+    marked: Vec<usize>,
+    /// Games waiting for a y/n answer before being downloaded
+    confirm: Option<Vec<usize>>,
+    /// Waiting for a y/n answer before quitting and aborting the running queue
+    confirm_quit: bool,
+    job: Option<Job>,
+    status: String,
+    should_quit: bool,
+}
+
+impl App {
+    fn new(games: Vec<Game>, downloader: Arc<Downloader>, external_download: bool) -> Self {
+        let mut app = Self {
+            games,
+            downloader,
+            external_download,
+            query: String::new(),
+            query_error: None,
+            filtered: Vec::new(),
+            table_state: TableState::default(),
+            marked: Vec::new(),
+            confirm: None,
+            confirm_quit: false,
+            job: None,
+            status: String::new(),
+            should_quit: false,
+        };
+        app.refilter();
+        app
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        mut receiver: mpsc::UnboundedReceiver<ProgressEvent>,
+    ) -> Result<()> {
+        let mut events = EventStream::new();
+        let mut tick = tokio::time::interval(Duration::from_millis(250));
+
+        while !self.should_quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => self.handle_key(key),
+                    Some(Err(e)) => return Err(e.into()),
+                    None => break,
+                    _ => {}
+                },
+                Some(event) = receiver.recv() => self.handle_progress(event),
+                _ = tick.tick() => {}
+            }
+
+            self.collect_finished_job().await;
+        }
+
+        if let Some(handle) = self.job.as_mut().and_then(|job| job.handle.take()) {
+            handle.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Recomputes the filtered list after the query changed.
+    fn refilter(&mut self) {
+        match query_games(self.downloader.config(), &self.games, &self.query) {
+            Ok(matches) => {
+                self.filtered = matches.into_iter().map(|hit| hit.index - 1).collect();
+                self.query_error = None;
+            }
+            Err(e) => {
+                self.query_error = Some(e.to_string());
+                return;
+            }
+        }
+        self.table_state
+            .select(if self.filtered.is_empty() { None } else { Some(0) });
+    }
+
+    fn highlighted(&self) -> Option<usize> {
+        self.table_state.selected().and_then(|i| self.filtered.get(i).copied())
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.filtered.is_empty() {
+            return;
+        }
+        let current = self.table_state.selected().unwrap_or(0) as isize;
+        let last = self.filtered.len() as isize - 1;
+        self.table_state.select(Some((current + delta).clamp(0, last) as usize));
+    }
+
+    fn toggle_mark(&mut self, index: usize) {
+        if let Some(position) = self.marked.iter().position(|&i| i == index) {
+            self.marked.remove(position);
+        } else {
+            self.marked.push(index);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if let Some(selection) = self.confirm.take() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.start_job(selection),
+                _ => self.status = "Cancelled".to_string(),
+            }
+            return;
+        }
+
+        if self.confirm_quit {
+            self.confirm_quit = false;
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.should_quit = true,
+                _ => self.status = "Cancelled".to_string(),
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('c') | KeyCode::Char('q') if ctrl => self.request_quit(),
+            KeyCode::Char('a') if ctrl => {
+                for index in self.filtered.clone() {
+                    if !self.marked.contains(&index) {
+                        self.marked.push(index);
+                    }
+                }
+            }
+            KeyCode::Char('x') if ctrl => self.marked.clear(),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.refilter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.refilter();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refilter();
+            }
+            KeyCode::Esc => {
+                if self.query.is_empty() {
+                    self.request_quit();
+                } else {
+                    self.query.clear();
+                    self.refilter();
+                }
+            }
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::Home => self.move_selection(isize::MIN / 2),
+            KeyCode::End => self.move_selection(isize::MAX / 2),
+            KeyCode::Tab => {
+                if let Some(index) = self.highlighted() {
+                    self.toggle_mark(index);
+                    self.move_selection(1);
+                }
+            }
+            KeyCode::Enter => self.request_download(),
+            _ => {}
+        }
+    }
+
+    /// Quits, asking first when a download queue would be aborted.
+    fn request_quit(&mut self) {
+        if self.job.as_ref().is_some_and(Job::is_running) {
+            self.confirm_quit = true;
+            self.status = "A download is running; quit and abort it? [y/N]".to_string();
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    /// Asks for confirmation before downloading the marked games (or the highlighted one).
+    fn request_download(&mut self) {
+        if self.job.as_ref().is_some_and(Job::is_running) {
+            self.status = "A download queue is already running".to_string();
+            return;
+        }
+        if self.external_download {
+            self.status = "EXTERNAL_ISO = 1 is not supported in the full-screen UI".to_string();
+            return;
+        }
+
+        let selection = if self.marked.is_empty() {
+            self.highlighted().into_iter().collect()
+        } else {
+            self.marked.clone()
+        };
+        if !selection.is_empty() {
+            self.confirm = Some(selection);
+        }
+    }
+
+    fn start_job(&mut self, selection: Vec<usize>) {
+        let games: Vec<Game> = selection.iter().map(|&i| self.games[i].clone()).collect();
+        let downloader = Arc::clone(&self.downloader);
+        let handle = tokio::spawn(async move {
+            let games: Vec<&Game> = games.iter().collect();
+            download_queue(&downloader, &games).await
+        });
+
+        self.marked.clear();
+        self.status = format!("Started {} download(s)", selection.len());
+        self.job = Some(Job {
+            handle: Some(handle),
+            current: None,
+            phase: None,
+            done: 0,
+            total: None,
+            log: Vec::new(),
+            results: Vec::new(),
+        });
+    }
+
+    async fn collect_finished_job(&mut self) {
+        let Some(job) = self.job.as_mut() else { return };
+        if !job.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            return;
+        }
+        if let Some(handle) = job.handle.take() {
+            job.results = handle.await.unwrap_or_default();
+            let failed = job.results.iter().filter(|r| !r.succeeded()).count();
+            self.status = format!(
+                "Queue finished: {} succeeded, {} failed",
+                job.results.len() - failed,
+                failed
+            );
+        }
+    }
+
+    fn handle_progress(&mut self, event: ProgressEvent) {
+        let Some(job) = self.job.as_mut() else { return };
+        match event {
+            ProgressEvent::GameStarted { index, total, title } => {
+                job.current = Some((index, total, title));
+                job.phase = None;
+            }
+            ProgressEvent::GameFinished { title, error } => {
+                let line = match error {
+                    None => format!("OK     {}", title),
+                    Some(error) => format!("FAILED {} ({})", title, error),
+                };
+                job.log.push(line);
+            }
+            ProgressEvent::PhaseStarted { phase, total } => {
+                job.phase = Some(phase);
+                job.done = 0;
+                job.total = total;
+            }
+            ProgressEvent::Progress {
+                phase, done, total, ..
+            } => {
+                job.phase = Some(phase);
+                job.done = done;
+                job.total = total;
+            }
+            ProgressEvent::Retry {
+                attempt,
+                max_attempts,
+                reason,
+                ..
+            } => job.log.push(format!("Retry {}/{}: {}", attempt, max_attempts, reason)),
+            ProgressEvent::Stalled { .. }
+            | ProgressEvent::Error { .. }
+            | ProgressEvent::PhaseFinished { .. } => {}
+            ProgressEvent::Message { message } => {
+                job.log.extend(
+                    message
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(str::to_string),
+                );
+            }
+        }
+        if job.log.len() > LOG_LINES {
+            let excess = job.log.len() - LOG_LINES;
+            job.log.drain(..excess);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let progress_height = if self.job.is_some() { 9 } else { 0 };
+        let [search_area, main_area, progress_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(progress_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .areas(main_area);
+
+        self.draw_search(frame, search_area);
+        self.draw_list(frame, list_area);
+        self.draw_details(frame, details_area);
+        if self.job.is_some() {
+            self.draw_progress(frame, progress_area);
+        }
+        self.draw_help(frame, help_area);
+
+        if let Some(selection) = &self.confirm {
+            self.draw_confirm(frame, selection);
+        }
+    }
+
+    fn draw_search(&self, frame: &mut Frame, area: Rect) {
+        let title = match &self.query_error {
+            Some(error) => format!(" Search ({}) ", error),
+            None => format!(" Search ({} of {} titles) ", self.filtered.len(), self.games.len()),
+        };
+        let search = Paragraph::new(Line::from(vec![
+            Span::raw(self.query.as_str()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ]))
+        .block(Block::bordered().title(title));
+        frame.render_widget(search, area);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.filtered.iter().map(|&index| {
+            let game = &self.games[index];
+            let mark = if self.marked.contains(&index) { "*" } else { " " };
+            Row::new(vec![
+                Cell::from(mark),
+                Cell::from(game.clean_title()),
+                Cell::from(game.region.clone().unwrap_or_default()),
+                Cell::from(game.size.clone()),
+            ])
+        });
+
+        let title = if self.marked.is_empty() {
+            " Titles ".to_string()
+        } else {
+            format!(" Titles ({} marked, {}) ", self.marked.len(), self.marked_size())
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Min(20),
+                Constraint::Length(12),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(vec!["", "Title", "Region", "Size"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let lines = match self.highlighted().map(|i| &self.games[i]) {
+            Some(game) => vec![
+                Line::from(Span::styled(
+                    game.clean_title(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Line::from(""),
+                Line::from(format!("System:  {}", game.game_type.name())),
+                Line::from(format!("Region:  {}", game.region.as_deref().unwrap_or("Unknown"))),
+                Line::from(format!(
+                    "Langs:   {}",
+                    if game.languages.is_empty() { "-".to_string() } else { game.languages.join(", ") }
+                )),
+                Line::from(format!(
+                    "Tags:    {}",
+                    if game.tags.is_empty() { "-".to_string() } else { game.tags.join(", ") }
+                )),
+                Line::from(format!("Size:    {}", game.size)),
+                Line::from(format!("File:    {}", game.title)),
+                Line::from(format!(
+                    "Output:  {}",
+                    if game.needs_decryption { game.output_iso_filename() } else { "as extracted".to_string() }
+                )),
+                Line::from(format!(
+                    "Decrypt: {}",
+                    if game.needs_decryption { "yes" } else { "no" }
+                )),
+            ],
+            None => vec![Line::from("No title selected")],
+        };
+
+        let details = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Details "));
+        frame.render_widget(details, area);
+    }
+
+    fn draw_progress(&self, frame: &mut Frame, area: Rect) {
+        let Some(job) = &self.job else { return };
+        let block = Block::bordered().title(match &job.current {
+            Some((index, total, title)) => format!(" [{}/{}] {} ", index, total, title),
+            None => " Queue ".to_string(),
+        });
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [gauge_area, log_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(inner);
+
+        let (ratio, label) = match (job.phase, job.total) {
+            (Some(phase), Some(total)) if total > 0 => {
+                let ratio = (job.done as f64 / total as f64).clamp(0.0, 1.0);
+                let amount = if phase == Phase::Extract && total < 1024 {
+                    format!("{}/{} files", job.done, total)
+                } else {
+                    format!("{} / {}", format_size(job.done), format_size(total))
+                };
+                (ratio, format!("{} {} ({:.0}%)", phase.label(), amount, ratio * 100.0))
+            }
+            (Some(phase), _) => (0.0, format!("{}...", phase.label())),
+            (None, _) if job.is_running() => (0.0, "Preparing...".to_string()),
+            (None, _) => (1.0, "Done".to_string()),
+        };
+        frame.render_widget(
+            Gauge::default()
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(ratio)
+                .label(label),
+            gauge_area,
+        );
+
+        let visible = log_area.height as usize;
+        let start = job.log.len().saturating_sub(visible);
+        let log: Vec<Line> = job.log[start..].iter().map(|l| Line::from(l.as_str())).collect();
+        frame.render_widget(Paragraph::new(log), log_area);
+    }
+
+    fn draw_help(&self, frame: &mut Frame, area: Rect) {
+        let help = if self.status.is_empty() {
+            "Type to filter | ↑/↓ PgUp/PgDn move | Tab mark | Ctrl-A mark all | Ctrl-X clear marks | Enter download | Esc clear/quit".to_string()
+        } else {
+            self.status.clone()
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+            area,
+        );
+    }
+
+    fn draw_confirm(&self, frame: &mut Frame, selection: &[usize]) {
+        let games: Vec<&Game> = selection.iter().map(|&i| &self.games[i]).collect();
+        let mut lines: Vec<Line> = games
+            .iter()
+            .take(8)
+            .map(|game| Line::from(format!("{} ({})", game.clean_title(), game.size)))
+            .collect();
+        if games.len() > 8 {
+            lines.push(Line::from(format!("... and {} more", games.len() - 8)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!("Total size: {}", describe_total_size(&games))));
+        lines.push(Line::from("Download? [y/N]"));
+
+        let area = frame.area();
+        let width = area.width.saturating_sub(10).min(90);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title(format!(" Download {} game(s) ", games.len()))),
+            popup,
+        );
+    }
+
+    fn marked_size(&self) -> String {
+        let games: Vec<&Game> = self.marked.iter().map(|&i| &self.games[i]).collect();
+        describe_total_size(&games)
+    }
+}
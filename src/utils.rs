@@ -1,7 +1,9 @@
-use crate::config::Config;
-use anyhow::Result;
+use crate::{config::Config, error::Error};
+use anyhow::{Context, Result};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Sets up the required folders for temporary files and ISO downloads.
 pub fn setup_folders(config: &Config) -> Result<()> {
@@ -23,5 +25,154 @@ fn check_folder(folder_path: &Path, folder_name: &str) -> Result<()> {
 /// Creates a folder and handles errors appropriately.
 fn create_folder(folder_path: &Path, folder_name: &str) -> Result<()> {
     fs::create_dir_all(folder_path)
-        .map_err(|e| anyhow::anyhow!("Error creating '{}' folder: {}", folder_name, e))
+        .map_err(|e| Error::filesystem("create", folder_path, e))
+        .with_context(|| format!("Error creating '{}' folder", folder_name))
+}
+
+/// Writes a file atomically: the content goes to a temporary file in the same folder,
+/// is flushed to disk, then renamed over the target, so a crash leaves either the old
+/// or the new file, never a truncated one.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = Path::new(&tmp_path);
+
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(tmp_path);
+        return Err(Error::filesystem("write", tmp_path, e).into());
+    }
+    fs::rename(tmp_path, path).map_err(|e| Error::filesystem("rename", tmp_path, e))?;
+    Ok(())
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Returns true if a cache last checked at `checked_at` (seconds since the Unix epoch)
+/// is older than the TTL. A TTL of zero never expires.
+pub fn is_expired(checked_at: Option<u64>, ttl: Duration) -> bool {
+    if ttl.is_zero() {
+        return false;
+    }
+    match checked_at {
+        Some(checked_at) => unix_now().saturating_sub(checked_at) >= ttl.as_secs(),
+        None => true,
+    }
+}
+
+/// Parses a size as directory listings show it into bytes: '4.2 GiB', '700 MB', '4.2G',
+/// '4294967296' or '1,234,567 bytes'. Commas group thousands when followed by exactly three
+/// digits or when there is also a decimal point, otherwise they are one ('4,2 GiB').
+/// Returns None for unknown or malformed sizes, such as '-' for folders.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let groups: Vec<&str> = number.split(',').collect();
+    let number = if number.contains('.') || groups[1..].iter().all(|group| group.len() == 3) {
+        groups.concat()
+    } else {
+        number.replace(',', ".")
+    };
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" | "bytes" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return None,
+    };
+
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Formats a byte count using binary units, e.g. '4.2 GiB'.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Parses a date as directory listings show it into 'YYYY-MM-DD HH:MM' (or 'YYYY-MM-DD'
+/// without a time), so dates compare as strings: '2023-12-05 14:22', '05-Dec-2023 14:22',
+/// '2023-Dec-05 14:22:33' or '2023-12-05T14:22:33Z'. Returns None for '-' or anything else.
+pub fn parse_listing_date(date: &str) -> Option<String> {
+    let date = date.trim();
+    let (day_part, time_part) = match date.split_once([' ', 'T']) {
+        Some((day, time)) => (day, Some(time.trim())),
+        None => (date, None),
+    };
+
+    let parts: Vec<&str> = day_part.split(['-', '/']).collect();
+    let [first, month, last] = parts[..] else {
+        return None;
+    };
+    // Year first ('2023-12-05') or last ('05-Dec-2023')
+    let (year, day) = if first.len() == 4 { (first, last) } else { (last, first) };
+    let year: u32 = year.parse().ok().filter(|_| year.len() == 4)?;
+    let month = month.parse::<u32>().ok().or_else(|| month_number(month))?;
+    let day: u32 = day.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut parsed = format!("{:04}-{:02}-{:02}", year, month, day);
+    let mut clock = time_part.unwrap_or_default().trim_end_matches('Z').split(':');
+    let hour = clock.next().and_then(|hour| hour.parse::<u32>().ok());
+    let minute = clock.next().and_then(|minute| minute.get(..2)?.parse::<u32>().ok());
+    if let (Some(hour), Some(minute)) = (hour, minute) {
+        parsed.push_str(&format!(" {:02}:{:02}", hour, minute));
+    }
+    Some(parsed)
+}
+
+/// Returns the month number of an English month name or abbreviation ('Dec', 'december').
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let prefix = name.get(..3)?.to_lowercase();
+    MONTHS.iter().position(|month| *month == prefix).map(|index| index as u32 + 1)
+}
+
+/// Formats seconds since the Unix epoch as a UTC 'YYYY-MM-DD HH:MM', the form of `parse_listing_date`.
+pub fn format_timestamp(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let minutes = seconds % 86_400 / 60;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+/// Converts days since the Unix epoch into a (year, month, day) date of the proleptic
+/// Gregorian calendar, as in Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
@@ -0,0 +1,85 @@
+use crate::{
+    catalog::Catalog,
+    config::Config,
+    models::Game,
+    progress::Reporter,
+    query::{run_query, Query, QueryContext},
+};
+use anyhow::Result;
+use serde::Serialize;
+
+/// Why a watched title is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertReason {
+    /// The title appeared in the game list
+    Listed,
+    /// The keys list gained a key for the title
+    KeyAdded,
+}
+
+/// A watched query matching a title that just appeared or just gained a key.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// The watch query that matched
+    pub watch: String,
+    pub title: String,
+    pub reason: AlertReason,
+    /// Whether a key is known for the title, or none is needed
+    pub has_key: bool,
+    /// Whether the title was put on the download queue
+    pub queued: bool,
+}
+
+/// Checks the watchlist against games that just appeared in the listing or just gained a key.
+/// Matches that have a key are queued for download when their watch auto-queues; the others
+/// are queued once their key shows up.
+pub fn check_watchlist(config: &Config, catalog: &Catalog, games: &[Game], reason: AlertReason) -> Result<Vec<Alert>> {
+    let mut alerts = Vec::new();
+    if games.is_empty() {
+        return Ok(alerts);
+    }
+
+    for watch in catalog.watches()? {
+        // Queries are checked when saved; one broken later must not stop the others
+        let query = match Query::parse(&watch.query) {
+            Ok(query) => query,
+            Err(e) => {
+                tracing::warn!("Skipping watch '{}': {:#}", watch.query, e);
+                continue;
+            }
+        };
+        let context = QueryContext::new(config, &query);
+        for hit in run_query(games, &query, &context) {
+            // Games of platforms without encryption need no key
+            let has_key =
+                reason == AlertReason::KeyAdded || hit.game.key_file.is_some() || !hit.game.needs_decryption;
+            let queued = watch.auto_queue && has_key && catalog.queue_download(&hit.game.title, &watch.query)?;
+            alerts.push(Alert {
+                watch: watch.query.clone(),
+                title: hit.game.clean_title(),
+                reason,
+                has_key,
+                queued,
+            });
+        }
+    }
+    Ok(alerts)
+}
+
+/// Reports watchlist alerts as status lines.
+pub fn report_alerts(reporter: &Reporter, alerts: &[Alert]) {
+    for alert in alerts {
+        let mut line = match (alert.reason, alert.has_key) {
+            (AlertReason::Listed, true) => format!("Watchlist '{}': new title {}", alert.watch, alert.title),
+            (AlertReason::Listed, false) => {
+                format!("Watchlist '{}': new title {} (no key yet)", alert.watch, alert.title)
+            }
+            (AlertReason::KeyAdded, _) => format!("Watchlist '{}': key now available for {}", alert.watch, alert.title),
+        };
+        if alert.queued {
+            line.push_str(", queued for download (run `watch download`)");
+        }
+        reporter.message(line);
+    }
+}
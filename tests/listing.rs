@@ -0,0 +1,89 @@
+use ps3_redump_downloader::listing::{detect_format, parse_listing, ListingFormat};
+use std::fs;
+use std::path::Path;
+
+/// Reads a saved listing, detects its format and returns the (name, size, size in bytes, date)
+/// of each listed file.
+fn parse_fixture(file: &str, format: ListingFormat) -> Vec<(String, String, Option<u64>, String)> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/listings").join(file);
+    let body = fs::read_to_string(path).unwrap();
+    assert_eq!(detect_format(&body), format, "format of {}", file);
+    parse_listing(&body)
+        .into_iter()
+        .map(|entry| (entry.name, entry.size, entry.size_bytes, entry.date))
+        .collect()
+}
+
+fn entry(name: &str, size: &str, size_bytes: Option<u64>, date: &str) -> (String, String, Option<u64>, String) {
+    (name.to_string(), size.to_string(), size_bytes, date.to_string())
+}
+
+#[test]
+fn myrient() {
+    assert_eq!(
+        parse_fixture("myrient.html", ListingFormat::Myrient),
+        [entry("Demon's Souls (Japan).zip", "23.4 GiB", None, "05-Dec-2023 10:42")]
+    );
+}
+
+#[test]
+fn apache_table() {
+    assert_eq!(
+        parse_fixture("apache_table.html", ListingFormat::Apache),
+        [entry("Demon's Souls (Japan).zip", "23G", None, "2023-12-05 10:42")]
+    );
+}
+
+#[test]
+fn apache_preformatted() {
+    assert_eq!(
+        parse_fixture("apache_preformatted.html", ListingFormat::Apache),
+        [entry("Demon's Souls (Japan).zip", "23G", None, "2023-12-05 10:42")]
+    );
+}
+
+#[test]
+fn nginx() {
+    // The link text is cut by nginx, so the name comes from the link
+    assert_eq!(
+        parse_fixture("nginx.html", ListingFormat::Nginx),
+        [entry(
+            "Demon's Souls (Japan) (En,Ja,Zh,Ko).zip",
+            "23.4 GiB",
+            Some(25_116_393_472),
+            "05-Dec-2023 10:42"
+        )]
+    );
+}
+
+#[test]
+fn nginx_json() {
+    assert_eq!(
+        parse_fixture("nginx.json", ListingFormat::NginxJson),
+        [entry("Demon's Souls (Japan).zip", "23.4 GiB", Some(25_116_393_472), "05-Dec-2023 10:42:00")]
+    );
+}
+
+#[test]
+fn lighttpd() {
+    assert_eq!(
+        parse_fixture("lighttpd.html", ListingFormat::Lighttpd),
+        [entry("Demon's Souls (Japan).zip", "23.4G", None, "2023-Dec-05 10:42:00")]
+    );
+}
+
+#[test]
+fn caddy() {
+    assert_eq!(
+        parse_fixture("caddy.html", ListingFormat::Caddy),
+        [entry("Demon's Souls (Japan).zip", "23.4 GiB", Some(25_116_393_472), "2023-12-05T10:42:00Z")]
+    );
+}
+
+#[test]
+fn plain_links() {
+    assert_eq!(
+        parse_fixture("links.html", ListingFormat::Unknown),
+        [entry("Demon's Souls (Japan).zip", "", None, "")]
+    );
+}
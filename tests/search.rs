@@ -0,0 +1,40 @@
+use ps3_redump_downloader::models::Game;
+use ps3_redump_downloader::search::search_games;
+
+fn games(titles: &[&str]) -> Vec<Game> {
+    titles
+        .iter()
+        .map(|title| Game::new_ps3(format!("{}.zip", title), String::new(), "1 GiB".to_string(), None))
+        .collect()
+}
+
+/// Returns the titles found for a search, best match first.
+fn found(games: &[Game], search: &str) -> Vec<String> {
+    search_games(games, search)
+        .into_iter()
+        .map(|hit| hit.game.clean_title())
+        .collect()
+}
+
+#[test]
+fn single_letter_numerals_match_their_value() {
+    let games = games(&[
+        "Grand Theft Auto V (USA)",
+        "Final Fantasy X-2 (Japan)",
+        "Final Fantasy XIII (Europe)",
+        "Rocky V (USA)",
+    ]);
+    assert_eq!(found(&games, "gta 5"), ["Grand Theft Auto V (USA)"]);
+    assert_eq!(found(&games, "grand theft auto v"), ["Grand Theft Auto V (USA)"]);
+    assert_eq!(found(&games, "final fantasy 10"), ["Final Fantasy X-2 (Japan)"]);
+    assert_eq!(found(&games, "final fantasy 13"), ["Final Fantasy XIII (Europe)"]);
+    assert_eq!(found(&games, "rocky 5"), ["Rocky V (USA)"]);
+}
+
+#[test]
+fn single_letters_are_not_read_as_numbers() {
+    let games = games(&["Rocky V (USA)", "Rocky 5 (Japan)"]);
+    // A title's 5 is a number, so searching for the letter does not find it
+    assert_eq!(found(&games, "rocky v"), ["Rocky V (USA)"]);
+    assert_eq!(found(&games, "rocky 5").len(), 2);
+}
@@ -0,0 +1,56 @@
+use ps3_redump_downloader::config::Config;
+use ps3_redump_downloader::listing::{detect_format, parse_listing, Fetched, ListingEntry, ListingFormat};
+use ps3_redump_downloader::source::{ArchiveSource, Source};
+use std::path::Path;
+
+#[tokio::test]
+async fn saved_metadata_lists_files_with_sizes_and_hashes() {
+    let config = Config::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config.ini")).unwrap();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/archive_metadata.json");
+    let source = ArchiveSource::from_saved_metadata(&path.display().to_string(), &path, &config);
+
+    let Fetched::Changed { body, .. } = source.fetch_listing(None).await.unwrap() else {
+        panic!("a saved copy is always read");
+    };
+    assert_eq!(detect_format(&body), ListingFormat::ArchiveOrg);
+
+    let entries = parse_listing(&body);
+    assert_eq!(
+        entries,
+        [
+            ListingEntry {
+                name: "Demon's Souls (Japan).zip".to_string(),
+                href: "Demon%27s%20Souls%20%28Japan%29.zip".to_string(),
+                size: "23.4 GiB".to_string(),
+                size_bytes: Some(25_116_393_472),
+                date: "2023-12-05 10:42".to_string(),
+                md5: Some("0f343b0931126a20f133d67c2b018a3b".to_string()),
+                sha1: Some("da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string()),
+            },
+            ListingEntry {
+                name: "Tokyo Jungle (Japan).zip".to_string(),
+                href: "Extras/Tokyo%20Jungle%20%28Japan%29.zip".to_string(),
+                size: "1.4 GiB".to_string(),
+                size_bytes: Some(1_473_249_280),
+                date: "2023-12-05 10:42".to_string(),
+                md5: Some("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+                sha1: Some("2fd4e1c67a2d28fced849ee1bb76e7391b93eb12".to_string()),
+            },
+            ListingEntry {
+                name: "redump-ps3-sample_meta.xml".to_string(),
+                href: "redump-ps3-sample_meta.xml".to_string(),
+                size: "2.0 KiB".to_string(),
+                size_bytes: Some(2048),
+                date: "2023-12-05 10:42".to_string(),
+                md5: None,
+                sha1: Some("de9f2c7fd25e1b3afad3e85a0bd17d9b100db4b3".to_string()),
+            },
+        ]
+    );
+
+    // Files are downloaded from the item named in the metadata, not from the file name
+    assert_eq!(
+        source.file_url(&entries[1].href),
+        "https://archive.org/download/redump-ps3-sample/Extras/Tokyo%20Jungle%20%28Japan%29.zip"
+    );
+}